@@ -0,0 +1,375 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rustml::bayes_classifier::{NaiveBayesClassifier, TokenizeBorrowed, Variant};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+const TRAINING_DOCUMENTS: usize = 100_000;
+const HELD_OUT_DOCUMENTS: usize = 1_000;
+const VOCABULARY_SIZE: u64 = 50_000;
+const CLASSES: u64 = 4;
+
+struct Document {
+    string: String,
+}
+
+impl TokenizeBorrowed for Document {
+    type Borrowed = str;
+
+    fn tokenize_borrowed<'a>(&'a self) -> impl Iterator<Item = Cow<'a, str>>
+    where
+        str: 'a,
+    {
+        self.string.split(' ').map(Cow::Borrowed)
+    }
+}
+
+impl TokenizeBorrowed for &Document {
+    type Borrowed = str;
+
+    fn tokenize_borrowed<'a>(&'a self) -> impl Iterator<Item = Cow<'a, str>>
+    where
+        str: 'a,
+    {
+        (**self).tokenize_borrowed()
+    }
+}
+
+/// Xorshift, so that every run benchmarks the same corpus.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    /// Skewed towards small values, like word frequencies.
+    fn skewed_below(&mut self, bound: u64) -> u64 {
+        let uniform = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+
+        (uniform.powi(3) * bound as f64) as u64
+    }
+}
+
+/// Documents of 20 to 60 words. Half the words are drawn from vocabulary shared by every class,
+/// the other half from a ranking of the vocabulary peculiar to the document's class.
+fn corpus(random: &mut Random, documents: usize) -> Vec<(u64, Document)> {
+    (0..documents)
+        .map(|_| {
+            let class = random.below(CLASSES);
+            let length = 20 + random.below(41);
+            let words: Vec<String> = (0..length)
+                .map(|_| {
+                    let rank = random.skewed_below(VOCABULARY_SIZE);
+                    let word = if random.below(2) == 0 {
+                        rank
+                    } else {
+                        (rank * (2 * class + 1) + class * 7_919) % VOCABULARY_SIZE
+                    };
+
+                    format!("w{}", word)
+                })
+                .collect();
+
+            (
+                class,
+                Document {
+                    string: words.join(" "),
+                },
+            )
+        })
+        .collect()
+}
+
+fn trained(variant: Variant, training: &[(u64, Document)]) -> NaiveBayesClassifier<u64> {
+    let mut classifier = NaiveBayesClassifier::new().with_variant(variant);
+
+    for (class, document) in training.iter() {
+        classifier.train(*class, document);
+    }
+
+    classifier
+}
+
+/// Per-class counts of one token, or of the classes themselves.
+#[derive(Default)]
+struct Counts {
+    total: f64,
+    per_class: HashMap<u64, f64>,
+}
+
+impl Counts {
+    fn add(&mut self, class: u64, amount: f64) {
+        self.total += amount;
+        *self.per_class.entry(class).or_insert(0.0) += amount;
+    }
+
+    fn count(&self, class: u64) -> f64 {
+        self.per_class.get(&class).cloned().unwrap_or(0.0)
+    }
+}
+
+/// The classifier's scoring over counts kept in a map of per-class counts for every token, the
+/// storage it used before tokens were interned into a dense count matrix. The sums over the whole
+/// vocabulary, Bernoulli absences and complement normalizers, are cached per class just as the
+/// classifier caches them, so that the "analyze reference" group differs from "analyze" only in
+/// how counts are stored and looked up.
+///
+/// Scores match the classifier's defaults of Laplace smoothing, a smoothed slot for unknown
+/// tokens and priors fitted to document counts.
+struct Reference {
+    variant: Variant,
+    classes: Vec<u64>,
+    class_tokens: Counts,
+    class_documents: Counts,
+    token_counts: HashMap<String, Counts>,
+    document_counts: HashMap<String, Counts>,
+    /// Bernoulli log probability of every vocabulary token being absent, per class.
+    absent: HashMap<u64, f64>,
+    /// Sum of the absolute complement log weights of every vocabulary token, per class.
+    normalizers: HashMap<u64, f64>,
+}
+
+impl Reference {
+    fn new(variant: Variant, training: &[(u64, Document)]) -> Reference {
+        let mut reference = Reference {
+            variant,
+            classes: Vec::new(),
+            class_tokens: Counts::default(),
+            class_documents: Counts::default(),
+            token_counts: HashMap::new(),
+            document_counts: HashMap::new(),
+            absent: HashMap::new(),
+            normalizers: HashMap::new(),
+        };
+
+        for (class, document) in training.iter() {
+            let tokens: Vec<&str> = document.string.split(' ').collect();
+            let distinct: HashSet<&str> = tokens.iter().cloned().collect();
+
+            for token in tokens.iter() {
+                reference
+                    .token_counts
+                    .entry(token.to_string())
+                    .or_default()
+                    .add(*class, 1.0);
+            }
+            for token in distinct {
+                reference
+                    .document_counts
+                    .entry(token.to_string())
+                    .or_default()
+                    .add(*class, 1.0);
+            }
+
+            if !reference.classes.contains(class) {
+                reference.classes.push(*class);
+            }
+            reference.class_tokens.add(*class, tokens.len() as f64);
+            reference.class_documents.add(*class, 1.0);
+        }
+
+        for &class in reference.classes.iter() {
+            let absent = reference
+                .document_counts
+                .values()
+                .map(|counts| (1.0 - reference.presence(class, Some(counts))).ln())
+                .sum();
+            let normalizer = reference
+                .token_counts
+                .values()
+                .map(|counts| reference.complement_weight(class, Some(counts)).abs())
+                .sum();
+
+            reference.absent.insert(class, absent);
+            reference.normalizers.insert(class, normalizer);
+        }
+
+        reference
+    }
+
+    /// Vocabulary size with one extra slot for unknown tokens.
+    fn vocabulary_size(&self) -> f64 {
+        self.token_counts.len() as f64 + 1.0
+    }
+
+    /// Bernoulli probability of a token with the document `counts` being present in `class`.
+    fn presence(&self, class: u64, counts: Option<&Counts>) -> f64 {
+        let count = counts.map_or(0.0, |counts| counts.count(class));
+
+        (count + 1.0) / (self.class_documents.count(class) + 2.0)
+    }
+
+    /// Complement log weight of a token with the token `counts` for `class`.
+    fn complement_weight(&self, class: u64, counts: Option<&Counts>) -> f64 {
+        let complement_count = counts.map_or(0.0, |counts| counts.total - counts.count(class));
+        let denominator =
+            self.class_tokens.total - self.class_tokens.count(class) + self.vocabulary_size();
+
+        ((complement_count + 1.0) / denominator).ln()
+    }
+
+    fn log_scores(&self, document: &Document) -> HashMap<u64, f64> {
+        let tokens: Vec<&str> = document.string.split(' ').collect();
+
+        self.classes
+            .iter()
+            .map(|&class| {
+                let log_prior =
+                    (self.class_documents.count(class) / self.class_documents.total).ln();
+                let log_score = match self.variant {
+                    Variant::Multinomial => {
+                        let denominator = self.class_tokens.count(class) + self.vocabulary_size();
+
+                        log_prior
+                            + tokens
+                                .iter()
+                                .map(|token| {
+                                    let count = self
+                                        .token_counts
+                                        .get(*token)
+                                        .map_or(0.0, |counts| counts.count(class));
+
+                                    ((count + 1.0) / denominator).ln()
+                                })
+                                .sum::<f64>()
+                    }
+                    Variant::Bernoulli => {
+                        let present: HashSet<&str> = tokens.iter().cloned().collect();
+
+                        log_prior
+                            + self.absent[&class]
+                            + present
+                                .iter()
+                                .map(|token| {
+                                    let counts = self.document_counts.get(*token);
+                                    let presence = self.presence(class, counts);
+                                    // Known tokens are taken back out of the cached absences.
+                                    let absence = match counts {
+                                        Some(_) => (1.0 - presence).ln(),
+                                        None => 0.0,
+                                    };
+
+                                    presence.ln() - absence
+                                })
+                                .sum::<f64>()
+                    }
+                    Variant::Complement => {
+                        -tokens
+                            .iter()
+                            .map(|token| {
+                                self.complement_weight(class, self.token_counts.get(*token))
+                            })
+                            .sum::<f64>()
+                            / self.normalizers[&class]
+                    }
+                };
+
+                (class, log_score)
+            })
+            .collect()
+    }
+}
+
+const VARIANTS: [Variant; 3] = [
+    Variant::Multinomial,
+    Variant::Bernoulli,
+    Variant::Complement,
+];
+
+fn train(c: &mut Criterion) {
+    let training = corpus(&mut Random { state: 0x2545_f491 }, TRAINING_DOCUMENTS);
+    let mut group = c.benchmark_group("train");
+
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(TRAINING_DOCUMENTS as u64));
+    group.bench_function("100k documents", |b| {
+        b.iter(|| trained(Variant::Multinomial, &training))
+    });
+    group.finish();
+}
+
+fn analyze(c: &mut Criterion) {
+    let mut random = Random { state: 0x2545_f491 };
+    let training = corpus(&mut random, TRAINING_DOCUMENTS);
+    let held_out = corpus(&mut random, HELD_OUT_DOCUMENTS);
+    let mut group = c.benchmark_group("analyze");
+
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(HELD_OUT_DOCUMENTS as u64));
+
+    for variant in VARIANTS.iter() {
+        let classifier = trained(*variant, &training);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{:?}", variant)),
+            &held_out,
+            |b, held_out| {
+                b.iter(|| {
+                    held_out
+                        .iter()
+                        .map(|(_, document)| classifier.analyze(document).len())
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn analyze_reference(c: &mut Criterion) {
+    let mut random = Random { state: 0x2545_f491 };
+    let training = corpus(&mut random, TRAINING_DOCUMENTS);
+    let held_out = corpus(&mut random, HELD_OUT_DOCUMENTS);
+    let mut group = c.benchmark_group("analyze reference");
+
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(HELD_OUT_DOCUMENTS as u64));
+
+    for variant in VARIANTS.iter() {
+        let reference = Reference::new(*variant, &training);
+        let classifier = trained(*variant, &training);
+
+        // The baseline is only a baseline if it scores what the classifier scores.
+        for (_, document) in held_out.iter() {
+            let expected = classifier.log_scores(document);
+
+            for (class, log_score) in reference.log_scores(document) {
+                assert!(
+                    (log_score - expected[&class]).abs() <= 1e-9 * log_score.abs().max(1.0),
+                    "{:?} reference scored {} where the classifier scored {}",
+                    variant,
+                    log_score,
+                    expected[&class]
+                );
+            }
+        }
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{:?}", variant)),
+            &held_out,
+            |b, held_out| {
+                b.iter(|| {
+                    held_out
+                        .iter()
+                        .map(|(_, document)| reference.log_scores(document).len())
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, train, analyze, analyze_reference);
+criterion_main!(benches);
@@ -1,4 +1,8 @@
-use rustml::bayes_classifier::{DataType, NaiveBayesClassifier, Tokenize};
+use rustml::bayes_classifier::{
+    Apostrophes, NaiveBayesClassifier, Outcome, RejectOption, TokenizeBorrowed, WordTokenizer,
+};
+use std::borrow::Cow;
+use std::env;
 use std::fmt::Display;
 use std::io;
 use std::string::String;
@@ -24,24 +28,17 @@ struct Content {
     string: String,
 }
 
-impl Tokenize for Content {
-    fn tokenize(&self) -> Vec<DataType> {
-        let split_by: char = ' ';
-        let char_whitelist: [char; 27] = [
-            split_by, 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
-            'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
-        ];
-
-        self.string
-            .trim()
-            .to_lowercase()
-            .chars()
-            .filter(|character| char_whitelist.contains(&character))
-            .map(|character| character.to_string())
-            .collect::<DataType>()
-            .split(split_by)
-            .map(|word| word.to_string())
-            .collect()
+impl TokenizeBorrowed for Content {
+    type Borrowed = str;
+
+    fn tokenize_borrowed<'a>(&'a self) -> impl Iterator<Item = Cow<'a, str>>
+    where
+        str: 'a,
+    {
+        // French elides articles and prepositions, so "d’euros" counts as "d" and "euros".
+        WordTokenizer::new()
+            .with_apostrophes(Apostrophes::Split)
+            .tokens(&self.string)
     }
 }
 
@@ -52,7 +49,10 @@ struct LanguageDetector {
 impl LanguageDetector {
     pub fn new() -> LanguageDetector {
         LanguageDetector {
-            brain: NaiveBayesClassifier::new(),
+            brain: NaiveBayesClassifier::new().with_reject_option(RejectOption {
+                min_confidence: 0.8,
+                min_margin: 0.5,
+            }),
         }
     }
 
@@ -103,36 +103,22 @@ fn main() {
 
     let mut input = String::new();
 
-    if let Ok(_) = io::stdin().read_line(&mut input) {
-        let to_analyze: Content = Content { string: input };
-        let probabilities = language_detector_bot.brain.analyze(to_analyze);
-
-        let mut most_probable: Option<Language> = None;
-        for class in language_detector_bot.brain.classes.iter() {
-            if let Some(class_probability) = probabilities.get(class) {
-                most_probable = match most_probable {
-                    Some(current_most_probable_language) => {
-                        let mut new_most_probable_language = current_most_probable_language;
-
-                        if let Some(current_highest_probability) =
-                            probabilities.get(&current_most_probable_language)
-                        {
-                            if current_highest_probability < class_probability {
-                                new_most_probable_language = *class;
-                            }
-                        }
-
-                        Some(new_most_probable_language)
-                    }
-                    None => Some(*class),
-                };
-            }
+    if io::stdin().read_line(&mut input).is_ok() {
+        let to_analyze: Content = Content {
+            string: input.clone(),
+        };
+
+        match language_detector_bot.brain.classify(to_analyze) {
+            Outcome::Class(prediction) => println!("Language: {}.", prediction.class),
+            Outcome::Unknown => println!("Unknown language."),
         }
 
-        if let Some(probable) = most_probable {
-            println!("Language: {}.", probable);
-        } else {
-            println!("Not enough data.");
+        if env::args().any(|argument| argument == "--explain") {
+            let mut explanation = language_detector_bot
+                .brain
+                .explain(Content { string: input });
+            explanation.sort_by_influence();
+            print!("\n{}", explanation);
         }
     } else {
         println!("Something went wrong.")
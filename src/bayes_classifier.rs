@@ -1,112 +1,2105 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
 use std::hash::Hash;
+use std::io;
+use std::sync::OnceLock;
+
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::{BufReader, BufWriter, Read, Write};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+mod binary;
+#[cfg(feature = "serde")]
+mod class_map;
+mod classes;
+mod explanation;
+mod features;
+mod gaussian;
+mod mixed;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pruning;
+mod tokenizer;
+mod vocabulary;
+
+pub use self::binary::BINARY_FORMAT_VERSION;
+use self::classes::Classes;
+pub use self::explanation::{Explanation, TokenContribution};
+pub use self::features::{ClassSummary, InformativeFeature};
+pub use self::gaussian::GaussianNaiveBayesClassifier;
+pub use self::mixed::{Column, MixedNaiveBayesClassifier, Value};
+pub use self::tokenizer::{fold_case, Apostrophes, Hyphens, WordTokenizer, Words};
+use self::vocabulary::{borrowed_id, Vocabulary};
 
 pub type DataType = String;
 pub type Number = f64;
 pub type ProbabilityMap<T> = HashMap<T, Number>;
 
-pub trait Tokenize {
-    fn tokenize(&self) -> Vec<DataType>;
+/// Counts at or below this are treated as zero and pruned.
+const EPSILON: Number = 1e-9;
+
+/// Version written into saved JSON models. Bump when the saved layout changes.
+pub const FORMAT_VERSION: u32 = 6;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Untraining would remove more occurrences of a token than were trained for the class.
+    InsufficientCounts {
+        /// The token's `Debug` rendering.
+        datum: String,
+    },
+    Io(io::Error),
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+    /// The saved model was written with a format version this build cannot read.
+    UnsupportedVersion {
+        found: u32,
+        expected: u32,
+    },
+    /// The saved model is truncated or otherwise not a model this crate wrote.
+    InvalidModel(String),
+    /// Two classifiers cannot be merged because they smooth differently.
+    SmoothingMismatch {
+        expected: Smoothing,
+        found: Smoothing,
+    },
+    /// Two classifiers cannot be merged because they treat unknown tokens differently.
+    UnknownTokensMismatch {
+        expected: UnknownTokens,
+        found: UnknownTokens,
+    },
+    /// A feature vector's length differs from the vectors the classifier was trained on.
+    FeatureLength {
+        expected: usize,
+        found: usize,
+    },
+    /// A record's value does not match the kind of its schema column.
+    ColumnType {
+        column: usize,
+        expected: String,
+    },
+    /// A training weight is negative or not finite.
+    InvalidWeight {
+        weight: Number,
+    },
+    /// A numeric feature, or a numeric value of a record, is not finite.
+    InvalidFeature {
+        feature: usize,
+        value: Number,
+    },
+    /// A kernel density column's bandwidth is not finite and positive.
+    InvalidBandwidth {
+        column: usize,
+        bandwidth: Number,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InsufficientCounts { datum } => {
+                write!(f, "cannot untrain more of {} than was trained", datum)
+            }
+            Error::Io(error) => write!(f, "{}", error),
+            #[cfg(feature = "serde")]
+            Error::Json(error) => write!(f, "{}", error),
+            Error::UnsupportedVersion { found, expected } => write!(
+                f,
+                "unsupported model format version {} (expected {})",
+                found, expected
+            ),
+            Error::InvalidModel(reason) => write!(f, "invalid model: {}", reason),
+            Error::SmoothingMismatch { expected, found } => write!(
+                f,
+                "cannot merge a model smoothed with {:?} into one smoothed with {:?}",
+                found, expected
+            ),
+            Error::UnknownTokensMismatch { expected, found } => write!(
+                f,
+                "cannot merge a model treating unknown tokens as {:?} into one treating them as {:?}",
+                found, expected
+            ),
+            Error::FeatureLength { expected, found } => {
+                write!(f, "expected {} features but found {}", expected, found)
+            }
+            Error::ColumnType { column, expected } => {
+                write!(f, "column {} expects a {} value", column, expected)
+            }
+            Error::InvalidWeight { weight } => {
+                write!(f, "weight {} is not finite and non-negative", weight)
+            }
+            Error::InvalidFeature { feature, value } => {
+                write!(f, "feature {} is {}, which is not finite", feature, value)
+            }
+            Error::InvalidBandwidth { column, bandwidth } => write!(
+                f,
+                "column {} has bandwidth {}, which is not finite and positive",
+                column, bandwidth
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            #[cfg(feature = "serde")]
+            Error::Json(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::Json(error)
+    }
+}
+
+/// Types a classifier can count as tokens.
+///
+/// `unknown` names the token that stands in for unseen input under `UnknownTokens::Token`.
+/// Types without one score unseen input as under `UnknownTokens::Smooth` instead.
+///
+/// Strings, integers, `char`, `bool` and byte strings are tokens already. Other types, such as an
+/// enum of categories, opt in with an empty implementation:
+///
+/// ```
+/// use rustml::bayes_classifier::Token;
+///
+/// #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// enum Colour {
+///     Red,
+///     Green,
+/// }
+///
+/// impl Token for Colour {}
+/// ```
+pub trait Token: Hash + Eq + Clone + fmt::Debug {
+    fn unknown() -> Option<Self> {
+        None
+    }
+}
+
+impl Token for String {
+    fn unknown() -> Option<String> {
+        Some(UNKNOWN_TOKEN.to_string())
+    }
+}
+
+impl<'a> Token for &'a str {
+    fn unknown() -> Option<&'a str> {
+        Some(UNKNOWN_TOKEN)
+    }
+}
+
+impl<'a> Token for Cow<'a, str> {
+    fn unknown() -> Option<Cow<'a, str>> {
+        Some(Cow::Borrowed(UNKNOWN_TOKEN))
+    }
+}
+
+macro_rules! impl_token {
+    ($($token:ty),*) => {
+        $(impl Token for $token {})*
+    };
+}
+
+impl_token!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, char, bool);
+impl_token!(Vec<u8>, Box<[u8]>);
+
+impl Token for &[u8] {}
+
+impl<const N: usize> Token for [u8; N] {}
+
+pub trait Tokenize<K = DataType> {
+    fn tokenize(&self) -> Vec<K>;
+
+    /// Tokens paired with how much each occurrence counts, for TF-IDF or BM25 style weighting.
+    /// Every token weighs 1 unless overridden.
+    fn weighted_tokens(&self) -> Vec<(K, Number)> {
+        self.tokenize()
+            .into_iter()
+            .map(|datum| (datum, 1.0))
+            .collect()
+    }
+}
+
+/// Tokenization that borrows from the input where it can, so that training and scoring do not
+/// allocate for every token. Every `Tokenize` type gets this through a blanket implementation
+/// that yields its owned tokens.
+pub trait TokenizeBorrowed<K = DataType> {
+    /// The form tokens are borrowed as, such as `str` for `String` tokens.
+    type Borrowed: ?Sized + Hash + Eq + ToOwned<Owned = K>;
+
+    fn tokenize_borrowed<'a>(&'a self) -> impl Iterator<Item = Cow<'a, Self::Borrowed>>
+    where
+        Self::Borrowed: 'a;
+
+    /// Tokens paired with how much each occurrence counts, as in `Tokenize::weighted_tokens`.
+    fn weighted_tokens_borrowed<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (Cow<'a, Self::Borrowed>, Number)>
+    where
+        Self::Borrowed: 'a,
+    {
+        self.tokenize_borrowed().map(|datum| (datum, 1.0))
+    }
+}
+
+impl<K: Hash + Eq + Clone, U: Tokenize<K>> TokenizeBorrowed<K> for U {
+    type Borrowed = K;
+
+    fn tokenize_borrowed<'a>(&'a self) -> impl Iterator<Item = Cow<'a, K>>
+    where
+        K: 'a,
+    {
+        self.tokenize().into_iter().map(Cow::Owned)
+    }
+
+    fn weighted_tokens_borrowed<'a>(&'a self) -> impl Iterator<Item = (Cow<'a, K>, Number)>
+    where
+        K: 'a,
+    {
+        self.weighted_tokens()
+            .into_iter()
+            .map(|(datum, datum_weight)| (Cow::Owned(datum), datum_weight))
+    }
 }
 
-pub struct Counts<T: Hash + Eq + Copy> {
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))
+)]
+pub struct Counts<T: Hash + Eq + Clone> {
     total: f64,
+    #[cfg_attr(feature = "serde", serde(with = "class_map"))]
     per_class: HashMap<T, Number>,
 }
 
-impl<T: Hash + Eq + Copy> Counts<T> {
+impl<T: Hash + Eq + Clone> Counts<T> {
     pub fn new() -> Counts<T> {
         Counts {
             total: 0.0,
             per_class: HashMap::new(),
         }
     }
+
+    pub fn merge(&mut self, other: &Counts<T>) {
+        self.total += other.total;
+
+        for (class, count) in other.per_class.iter() {
+            *self.per_class.entry(class.clone()).or_insert(0.0) += count;
+        }
+    }
+
+    fn count(&self, class: &T) -> Number {
+        self.per_class.get(class).cloned().unwrap_or(0.0)
+    }
+
+    /// Removes up to `amount` from `class`, dropping the class once it is used up. Only what the
+    /// class held comes off the total, so the total stays the sum of the classes.
+    fn remove(&mut self, class: &T, amount: Number) {
+        if let Some(count) = self.per_class.get_mut(class) {
+            if *count - amount <= EPSILON {
+                self.total -= *count;
+                self.per_class.remove(class);
+            } else {
+                self.total -= amount;
+                *count -= amount;
+            }
+        }
+
+        if self.per_class.is_empty() {
+            self.total = 0.0;
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone> Default for Counts<T> {
+    fn default() -> Counts<T> {
+        Counts::new()
+    }
+}
+
+/// Additive smoothing applied to the per-class token likelihoods.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Smoothing {
+    None,
+    /// Add-one smoothing.
+    #[default]
+    Laplace,
+    /// Add-alpha smoothing with an arbitrary finite, non-negative pseudocount.
+    Lidstone(Number),
+}
+
+impl Smoothing {
+    pub fn alpha(&self) -> Number {
+        match *self {
+            Smoothing::None => 0.0,
+            Smoothing::Laplace => 1.0,
+            Smoothing::Lidstone(alpha) => alpha,
+        }
+    }
+
+    /// Checks that the pseudocount is a count: finite and non-negative.
+    fn check(&self) -> Result<(), String> {
+        let alpha = self.alpha();
+
+        if alpha.is_finite() && alpha >= 0.0 {
+            Ok(())
+        } else {
+            Err(format!("smoothing pseudocount {} is not a count", alpha))
+        }
+    }
+}
+
+/// `Token::unknown` of the string token types, scored in place of unknown input under
+/// `UnknownTokens::Token`.
+pub const UNKNOWN_TOKEN: &str = "<unk>";
+
+/// How `analyze` treats input tokens that never appeared during training.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnknownTokens {
+    /// Skip unknown tokens entirely.
+    Ignore,
+    /// Score unknown tokens with the smoothing pseudocount alone, reserving one extra vocabulary
+    /// slot for them. Under `Smoothing::None` there is no pseudocount, so they are ignored.
+    #[default]
+    Smooth,
+    /// Score unknown tokens as `Token::unknown`, such as `UNKNOWN_TOKEN` for strings, which may
+    /// itself have been trained on.
+    Token,
+}
+
+/// The event model used to turn token counts into likelihoods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Variant {
+    /// Counts every occurrence of a token. Suited to longer documents.
+    #[default]
+    Multinomial,
+    /// Records only whether a token occurs in a document, and penalizes vocabulary that does not.
+    /// Suited to short texts such as subject lines and tags.
+    Bernoulli,
+    /// Estimates each class from the token counts of every other class, with normalized weights
+    /// and no class prior. Suited to heavily imbalanced classes. Needs smoothing with a positive
+    /// pseudocount.
+    Complement,
+}
+
+/// Where class priors come from when scoring.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: Hash + Eq + Serialize",
+        deserialize = "T: Hash + Eq + Deserialize<'de>"
+    ))
+)]
+pub enum Priors<T: Hash + Eq> {
+    /// Fraction of training documents in each class. The complement variant uses no prior.
+    #[default]
+    Fitted,
+    /// Every class equally likely.
+    Uniform,
+    /// Explicit prior probabilities, each finite and non-negative. Classes missing from the map
+    /// get a prior of zero.
+    Custom(#[cfg_attr(feature = "serde", serde(with = "class_map"))] ProbabilityMap<T>),
+}
+
+impl<T: Hash + Eq> Priors<T> {
+    fn check(&self) -> Result<(), String> {
+        match self {
+            Priors::Custom(probabilities) => probabilities
+                .values()
+                .find(|probability| !(probability.is_finite() && **probability >= 0.0))
+                .map_or(Ok(()), |probability| {
+                    Err(format!(
+                        "prior probability {} is not a probability",
+                        probability
+                    ))
+                }),
+            Priors::Fitted | Priors::Uniform => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prediction<T> {
+    pub class: T,
+    pub confidence: Number,
+}
+
+/// Result of `classify`, which refuses to pick a class when the classifier is unsure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome<T> {
+    Class(Prediction<T>),
+    Unknown,
 }
 
-pub struct NaiveBayesClassifier<T: Hash + Eq + Copy> {
-    pub classes: Vec<T>,
+/// Thresholds below which `classify` abstains.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RejectOption {
+    /// Minimum posterior of the top class.
+    pub min_confidence: Number,
+    /// Minimum posterior difference between the top two classes.
+    pub min_margin: Number,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: Hash + Eq + Clone + Serialize, K: Token + Serialize",
+        deserialize = "T: Hash + Eq + Clone + Deserialize<'de>, K: Token + Deserialize<'de>"
+    ))
+)]
+pub struct NaiveBayesClassifier<T: Hash + Eq + Clone, K: Token = DataType> {
+    classes: Classes<T>,
     class_counts: Counts<T>,
-    datum_counts: HashMap<DataType, Counts<T>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    class_document_counts: Counts<T>,
+    datum_counts: Vocabulary<K>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    variant: Variant,
+    #[cfg_attr(feature = "serde", serde(default))]
+    priors: Priors<T>,
+    smoothing: Smoothing,
+    unknown_tokens: UnknownTokens,
+    reject_option: RejectOption,
+    /// Built on first use by scoring, and cleared by anything that changes the counts or the
+    /// settings they depend on.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tables: OnceLock<ScoringTables>,
+}
+
+impl<T: Hash + Eq + Clone, K: Token> Default for NaiveBayesClassifier<T, K> {
+    fn default() -> NaiveBayesClassifier<T, K> {
+        NaiveBayesClassifier::new()
+    }
+}
+
+impl<K: Token> NaiveBayesClassifier<String, K> {
+    /// A classifier labelled with strings, for class names that are only known at runtime, such
+    /// as those read from data or a config file.
+    pub fn with_string_labels() -> NaiveBayesClassifier<String, K> {
+        NaiveBayesClassifier::new()
+    }
 }
 
-impl<T: Hash + Eq + Copy> NaiveBayesClassifier<T> {
-    pub fn new() -> NaiveBayesClassifier<T> {
+impl<T: Hash + Eq + Clone, K: Token> NaiveBayesClassifier<T, K> {
+    pub fn new() -> NaiveBayesClassifier<T, K> {
         NaiveBayesClassifier {
-            classes: Vec::new(),
+            classes: Classes::new(),
             class_counts: Counts::new(),
-            datum_counts: HashMap::new(),
+            class_document_counts: Counts::new(),
+            datum_counts: Vocabulary::default(),
+            variant: Variant::default(),
+            priors: Priors::default(),
+            smoothing: Smoothing::default(),
+            unknown_tokens: UnknownTokens::default(),
+            reject_option: RejectOption::default(),
+            tables: OnceLock::new(),
         }
     }
 
-    pub fn train<U: Tokenize>(&mut self, class: T, data: U) {
-        let iterator: Number = 1.0;
+    /// # Panics
+    ///
+    /// Panics if `variant` is `Variant::Complement` and the smoothing has no pseudocount.
+    pub fn with_variant(mut self, variant: Variant) -> NaiveBayesClassifier<T, K> {
+        self.variant = variant;
+        self.tables.take();
+        self.assert_settings();
+        self
+    }
 
-        for datum in data.tokenize() {
-            let recorded_word_counts = self.datum_counts.entry(datum).or_insert(Counts::new());
+    /// # Panics
+    ///
+    /// Panics if a `Priors::Custom` probability is negative or not finite.
+    pub fn with_priors(mut self, priors: Priors<T>) -> NaiveBayesClassifier<T, K> {
+        self.priors = priors;
+        self.assert_settings();
+        self
+    }
 
-            recorded_word_counts.total += iterator;
-            recorded_word_counts
-                .per_class
-                .entry(class)
-                .and_modify(|entry| *entry += iterator)
-                .or_insert(iterator);
+    /// # Panics
+    ///
+    /// Panics if the pseudocount of `smoothing` is negative or not finite, or if it is zero and
+    /// the variant is `Variant::Complement`.
+    pub fn with_smoothing(mut self, smoothing: Smoothing) -> NaiveBayesClassifier<T, K> {
+        self.smoothing = smoothing;
+        self.tables.take();
+        self.assert_settings();
+        self
+    }
+
+    pub fn with_unknown_tokens(
+        mut self,
+        unknown_tokens: UnknownTokens,
+    ) -> NaiveBayesClassifier<T, K> {
+        self.unknown_tokens = unknown_tokens;
+        self.tables.take();
+        self
+    }
+
+    pub fn with_reject_option(mut self, reject_option: RejectOption) -> NaiveBayesClassifier<T, K> {
+        self.reject_option = reject_option;
+        self
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn priors(&self) -> &Priors<T> {
+        &self.priors
+    }
+
+    pub fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
+    pub fn unknown_tokens(&self) -> UnknownTokens {
+        self.unknown_tokens
+    }
+
+    pub fn reject_option(&self) -> RejectOption {
+        self.reject_option
+    }
+
+    pub fn classes(&self) -> &[T] {
+        &self.classes
+    }
+
+    /// Number of training documents labelled `class`, which the fitted priors are based on.
+    pub fn class_document_count(&self, class: &T) -> Number {
+        self.class_document_counts.count(class)
+    }
+
+    /// Number of token occurrences trained for `class`.
+    pub fn class_token_count(&self, class: &T) -> Number {
+        self.class_counts.count(class)
+    }
+
+    pub fn vocabulary_size(&self) -> usize {
+        self.datum_counts.len()
+    }
+
+    /// Fills in document counts for models saved before they were tracked, treating every
+    /// trained token occurrence as a one-token document so that existing scores are unchanged.
+    fn estimate_document_counts(&mut self) {
+        self.tables.take();
+        self.class_document_counts = self.class_counts.clone();
+        self.datum_counts.estimate_documents();
+    }
+
+    fn class_index_or_insert(&mut self, class: T) -> usize {
+        match self.classes.column(&class) {
+            Some(index) => index,
+            None => {
+                self.classes.push(class);
+                self.datum_counts.push_column();
+                self.classes.len() - 1
+            }
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `data` weighs a token negatively or not finitely.
+    pub fn train<U: TokenizeBorrowed<K>>(&mut self, class: T, data: U) {
+        self.train_weighted(class, data, 1.0)
+            .expect("token weights must be finite and non-negative");
+    }
+
+    /// Trains on `data` with every token count scaled by `weight`, for importance sampling or
+    /// for trusting some sources more than others. Fails without changing the model if `weight`
+    /// or a token's weight is negative or not finite. Tokens that end up weighing nothing are
+    /// skipped, so a `weight` of zero leaves the model as it was.
+    pub fn train_weighted<U: TokenizeBorrowed<K>>(
+        &mut self,
+        class: T,
+        data: U,
+        weight: Number,
+    ) -> Result<(), Error> {
+        let mut data: Vec<(Cow<U::Borrowed>, Number)> = data.weighted_tokens_borrowed().collect();
+
+        check_weight(weight)?;
+        for (_, datum_weight) in data.iter() {
+            check_weight(*datum_weight)?;
+        }
+
+        data.retain(|(_, datum_weight)| weight * datum_weight > 0.0);
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        self.tables.take();
+
+        let mut document: HashSet<usize> = HashSet::new();
+        let mut column: Option<usize> = None;
+        let mut trained: Number = 0.0;
+
+        for (datum, datum_weight) in data {
+            let iterator: Number = weight * datum_weight;
+            let column = *column.get_or_insert_with(|| self.class_index_or_insert(class.clone()));
+            let id = match borrowed_id(&self.datum_counts, &*datum) {
+                Some(id) => id,
+                None => self.datum_counts.intern(datum.into_owned()),
+            };
+
+            self.datum_counts.add(id, column, iterator);
+
+            if document.insert(id) {
+                self.datum_counts.add_documents(id, column, weight);
+            }
+
+            trained += iterator;
+        }
 
-            self.class_counts.total += iterator;
+        if !document.is_empty() {
+            self.class_counts.total += trained;
             self.class_counts
+                .per_class
+                .entry(class.clone())
+                .and_modify(|entry| *entry += trained)
+                .or_insert(trained);
+
+            self.class_document_counts.total += weight;
+            self.class_document_counts
                 .per_class
                 .entry(class)
-                .and_modify(|entry| *entry += iterator)
-                .or_insert(iterator);
+                .and_modify(|entry| *entry += weight)
+                .or_insert(weight);
+        }
 
-            if !self.classes.contains(&class) {
-                self.classes.push(class);
+        Ok(())
+    }
+
+    pub fn train_batch<I, U>(&mut self, batch: I)
+    where
+        I: IntoIterator<Item = (T, U)>,
+        U: TokenizeBorrowed<K>,
+    {
+        for (class, data) in batch {
+            self.train(class, data);
+        }
+    }
+
+    /// Reverses an earlier `train` call. Fails without changing the model if `data` contains a
+    /// token more often than it was trained for `class`.
+    pub fn untrain<U: TokenizeBorrowed<K>>(&mut self, class: T, data: U) -> Result<(), Error> {
+        self.untrain_weighted(class, data, 1.0)
+    }
+
+    /// Reverses an earlier `train_weighted` call with the same `weight`.
+    pub fn untrain_weighted<U: TokenizeBorrowed<K>>(
+        &mut self,
+        class: T,
+        data: U,
+        weight: Number,
+    ) -> Result<(), Error> {
+        check_weight(weight)?;
+
+        let mut to_remove: HashMap<Cow<U::Borrowed>, Number> = HashMap::new();
+
+        for (datum, datum_weight) in data.weighted_tokens_borrowed() {
+            check_weight(datum_weight)?;
+            if weight * datum_weight > 0.0 {
+                *to_remove.entry(datum).or_insert(0.0) += weight * datum_weight;
+            }
+        }
+
+        let column = match self.classes.column(&class) {
+            Some(column) => column,
+            None => match to_remove.into_iter().next() {
+                Some((datum, _)) => {
+                    return Err(Error::InsufficientCounts {
+                        datum: format!("{:?}", datum.into_owned()),
+                    })
+                }
+                None => return Ok(()),
+            },
+        };
+
+        for (datum, amount) in to_remove.iter() {
+            let trained = borrowed_id(&self.datum_counts, &**datum)
+                .map_or(0.0, |id| self.datum_counts.row(id)[column]);
+
+            if trained + EPSILON < *amount {
+                return Err(Error::InsufficientCounts {
+                    datum: format!("{:?}", datum.clone().into_owned()),
+                });
+            }
+        }
+
+        self.tables.take();
+
+        let has_data = !to_remove.is_empty();
+
+        for (datum, amount) in to_remove {
+            if let Some(id) = borrowed_id(&self.datum_counts, &*datum) {
+                self.datum_counts.add(id, column, -amount);
+                self.datum_counts.add_documents(id, column, -weight);
+
+                if self.datum_counts.row(id)[column] <= EPSILON {
+                    self.datum_counts.set(id, column, 0.0);
+                    self.datum_counts.set_documents(id, column, 0.0);
+                }
+
+                if self.datum_counts.total(id) <= EPSILON {
+                    self.datum_counts.remove(&datum.into_owned());
+                }
+            }
+
+            self.class_counts.remove(&class, amount);
+        }
+
+        // A class keeps as many documents as its most widespread remaining token appears in, so
+        // that untraining part of a document leaves the class a prior for what is left.
+        if has_data {
+            let remaining = self
+                .datum_counts
+                .ids()
+                .map(|id| self.datum_counts.document_row(id)[column])
+                .fold(0.0, Number::max);
+            let document_count = self.class_document_counts.count(&class);
+            let removed = weight.min(document_count - remaining).max(0.0);
+
+            if removed > 0.0 {
+                self.class_document_counts.remove(&class, removed);
+            }
+        }
+
+        if self.class_counts.count(&class) <= EPSILON {
+            let document_count = self.class_document_counts.count(&class);
+
+            self.class_document_counts.remove(&class, document_count);
+            self.classes.remove(column);
+            self.datum_counts.remove_column(column);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the counts of another classifier, as if its training data had been trained here.
+    /// Fails unless both smooth counts and treat unknown tokens the same way.
+    pub fn merge(&mut self, other: &NaiveBayesClassifier<T, K>) -> Result<(), Error> {
+        if self.smoothing != other.smoothing {
+            return Err(Error::SmoothingMismatch {
+                expected: self.smoothing,
+                found: other.smoothing,
+            });
+        }
+        if self.unknown_tokens != other.unknown_tokens {
+            return Err(Error::UnknownTokensMismatch {
+                expected: self.unknown_tokens,
+                found: other.unknown_tokens,
+            });
+        }
+
+        self.tables.take();
+
+        let columns: Vec<usize> = other
+            .classes
+            .iter()
+            .map(|class| self.class_index_or_insert(class.clone()))
+            .collect();
+
+        for (datum, other_id) in other.datum_counts.entries() {
+            let id = self.datum_counts.intern(datum.clone());
+
+            for (other_column, count) in other.datum_counts.row(other_id).iter().enumerate() {
+                self.datum_counts.add(id, columns[other_column], *count);
             }
+
+            let document_row = other.datum_counts.document_row(other_id);
+            for (other_column, count) in document_row.iter().enumerate() {
+                self.datum_counts
+                    .add_documents(id, columns[other_column], *count);
+            }
+        }
+
+        self.class_counts.merge(&other.class_counts);
+        self.class_document_counts
+            .merge(&other.class_document_counts);
+
+        Ok(())
+    }
+
+    pub fn log_scores<U: TokenizeBorrowed<K>>(&self, data: U) -> ProbabilityMap<T> {
+        self.score(data.weighted_tokens_borrowed(), &self.priors).0
+    }
+
+    pub fn analyze<U: TokenizeBorrowed<K>>(&self, data: U) -> ProbabilityMap<T> {
+        normalize(&self.log_scores(data))
+    }
+
+    /// Like `log_scores`, but with `priors` in place of the classifier's own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a `Priors::Custom` probability is negative or not finite.
+    pub fn log_scores_with_priors<U: TokenizeBorrowed<K>>(
+        &self,
+        data: U,
+        priors: &Priors<T>,
+    ) -> ProbabilityMap<T> {
+        if let Err(reason) = priors.check() {
+            panic!("{}", reason);
         }
+
+        self.score(data.weighted_tokens_borrowed(), priors).0
     }
 
-    pub fn analyze<U: Tokenize>(&self, data: U) -> ProbabilityMap<T> {
-        let mut probability_map: ProbabilityMap<T> = ProbabilityMap::new();
+    /// Like `analyze`, but with `priors` in place of the classifier's own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a `Priors::Custom` probability is negative or not finite.
+    pub fn analyze_with_priors<U: TokenizeBorrowed<K>>(
+        &self,
+        data: U,
+        priors: &Priors<T>,
+    ) -> ProbabilityMap<T> {
+        normalize(&self.log_scores_with_priors(data, priors))
+    }
 
-        for datum in data.tokenize() {
-            if let Some(datum_counts) = self.datum_counts.get(&datum) {
-                let total_datum_count = datum_counts.total;
+    pub fn predict<U: TokenizeBorrowed<K>>(&self, data: U) -> Option<Prediction<T>> {
+        self.predict_top_k(data, 1).into_iter().next()
+    }
 
-                for class in self.classes.iter() {
-                    let mut new_probability: Number = 0.01;
+    /// Like `predict`, but returns `None` when none of the input tokens were seen in training.
+    pub fn predict_known<U: TokenizeBorrowed<K>>(&self, data: U) -> Option<Prediction<T>> {
+        let (log_scores, known_datum_count) =
+            self.score(data.weighted_tokens_borrowed(), &self.priors);
 
-                    if let Some(datum_count_in_class) = datum_counts.per_class.get(&class) {
-                        let mut probability_of_datum_given_class: Number =
-                            *datum_count_in_class / total_datum_count;
+        if known_datum_count == 0 {
+            return None;
+        }
 
-                        if probability_of_datum_given_class == 1.0 {
-                            probability_of_datum_given_class = 0.99;
-                        }
+        rank(&self.classes, &normalize(&log_scores))
+            .into_iter()
+            .next()
+    }
+
+    pub fn predict_top_k<U: TokenizeBorrowed<K>>(&self, data: U, k: usize) -> Vec<Prediction<T>> {
+        let mut ranking = rank(&self.classes, &self.analyze(data));
+        ranking.truncate(k);
+        ranking
+    }
 
-                        new_probability = match probability_map.get(&class) {
-                            Some(existing_probability) => {
-                                existing_probability * probability_of_datum_given_class
-                            }
-                            None => probability_of_datum_given_class,
-                        };
+    pub fn classify<U: TokenizeBorrowed<K>>(&self, data: U) -> Outcome<T> {
+        let (log_scores, known_datum_count) =
+            self.score(data.weighted_tokens_borrowed(), &self.priors);
 
-                        // TODO: Compensate for rarer datum here.
-                    }
+        if known_datum_count == 0 {
+            return Outcome::Unknown;
+        }
+
+        let ranking = rank(&self.classes, &normalize(&log_scores));
+
+        match (ranking.first(), ranking.get(1)) {
+            (Some(best), runner_up) => {
+                let margin = best.confidence - runner_up.map_or(0.0, |second| second.confidence);
+
+                if !(best.confidence.is_finite() && best.confidence > 0.0)
+                    || best.confidence < self.reject_option.min_confidence
+                    || margin < self.reject_option.min_margin
+                {
+                    Outcome::Unknown
+                } else {
+                    Outcome::Class(best.clone())
+                }
+            }
+            (None, _) => Outcome::Unknown,
+        }
+    }
+
+    fn score<'a, B>(
+        &self,
+        data: impl Iterator<Item = (Cow<'a, B>, Number)>,
+        priors: &Priors<T>,
+    ) -> (ProbabilityMap<T>, usize)
+    where
+        B: ?Sized + Hash + Eq + ToOwned<Owned = K> + 'a,
+    {
+        let tables = self.tables();
+        let mut log_likelihoods: Vec<Number> = vec![0.0; self.classes.len()];
+        let mut known_datum_count: usize = 0;
+        let mut present: HashSet<usize> = HashSet::new();
+        let mut unknown: HashSet<Cow<'a, B>> = HashSet::new();
+
+        for (datum, datum_weight) in data {
+            let lookup = self.lookup(tables, borrowed_id(&self.datum_counts, &*datum));
+            match lookup {
+                Lookup::Known(_) => known_datum_count += 1,
+                Lookup::Unknown(_) => {}
+                Lookup::Skipped => continue,
+            }
+
+            // A Bernoulli token counts once per row, or once per distinct token without one.
+            if self.variant == Variant::Bernoulli {
+                let first_presence = match lookup.id() {
+                    Some(id) => present.insert(id),
+                    None => unknown.insert(datum),
+                };
 
-                    probability_map.insert(*class, new_probability);
+                if !first_presence {
+                    continue;
                 }
             }
+
+            self.add_token_terms(tables, lookup.id(), datum_weight, &mut log_likelihoods);
+        }
+
+        if self.variant == Variant::Bernoulli {
+            let baseline = self.bernoulli_baseline(tables, &present);
+
+            for (log_likelihood, absent) in log_likelihoods.iter_mut().zip(baseline) {
+                *log_likelihood += absent;
+            }
         }
 
-        for class in self.classes.iter() {
-            if let Some(&existing_probability) = probability_map.get(&class) {
-                if let Some(class_count) = self.class_counts.per_class.get(&class) {
-                    let probability_of_class: Number = *class_count / self.class_counts.total;
+        let log_scores = self
+            .classes
+            .iter()
+            .zip(log_likelihoods)
+            .zip(self.log_priors(tables, priors))
+            .map(|((class, log_likelihood), log_prior)| (class.clone(), log_likelihood + log_prior))
+            .collect();
+
+        (log_scores, known_datum_count)
+    }
+
+    /// Splits the log likelihood of `data` into one term per distinct input token and class,
+    /// plus whatever the variant adds independently of the input tokens.
+    fn breakdown<'a, B>(
+        &self,
+        data: impl Iterator<Item = (Cow<'a, B>, Number)>,
+    ) -> Breakdown<Cow<'a, B>>
+    where
+        B: ?Sized + Hash + Eq + ToOwned<Owned = K> + 'a,
+    {
+        let tables = self.tables();
+        let mut positions: HashMap<Cow<'a, B>, usize> = HashMap::new();
+        let mut looked_up: Vec<(Cow<'a, B>, Number, Lookup)> = Vec::new();
+        let mut unknown: Vec<Cow<'a, B>> = Vec::new();
 
-                    probability_map.insert(*class, existing_probability * probability_of_class);
+        for (datum, datum_weight) in data {
+            if let Some(&position) = positions.get(&datum) {
+                looked_up[position].1 += datum_weight;
+                continue;
+            }
+
+            let lookup = self.lookup(tables, borrowed_id(&self.datum_counts, &*datum));
+            match lookup {
+                Lookup::Known(_) => {}
+                Lookup::Unknown(_) => unknown.push(datum.clone()),
+                Lookup::Skipped => {
+                    if !unknown.contains(&datum) {
+                        unknown.push(datum);
+                    }
+                    continue;
                 }
             }
+
+            positions.insert(datum.clone(), looked_up.len());
+            looked_up.push((datum, datum_weight, lookup));
+        }
+
+        let mut present: HashSet<usize> = HashSet::new();
+
+        let tokens = looked_up
+            .into_iter()
+            .map(|(datum, datum_weight, lookup)| {
+                let mut log_likelihoods: Vec<Number> = vec![0.0; self.classes.len()];
+                let first_presence = lookup.id().is_none_or(|id| present.insert(id));
+
+                if self.variant != Variant::Bernoulli || first_presence {
+                    self.add_token_terms(tables, lookup.id(), datum_weight, &mut log_likelihoods);
+                }
+
+                TokenTerms::new(datum, datum_weight, lookup, log_likelihoods)
+            })
+            .collect();
+
+        let baseline = match self.variant {
+            Variant::Bernoulli => self.bernoulli_baseline(tables, &present),
+            Variant::Multinomial | Variant::Complement => vec![0.0; self.classes.len()],
+        };
+
+        Breakdown {
+            tokens,
+            baseline,
+            unknown,
         }
+    }
+
+    /// Adds the terms of one distinct input token, scored from row `id` if it has one, to
+    /// `log_likelihoods`, which is indexed by class column.
+    ///
+    /// A Bernoulli token contributes the log probability of appearing whatever its weight; the
+    /// caller must make sure each row is only added once.
+    fn add_token_terms(
+        &self,
+        tables: &ScoringTables,
+        id: Option<usize>,
+        datum_weight: Number,
+        log_likelihoods: &mut [Number],
+    ) {
+        let alpha = self.smoothing.alpha();
+        let columns = log_likelihoods.iter_mut().zip(tables.denominators.iter());
+
+        match self.variant {
+            Variant::Multinomial => {
+                let row = id.map(|id| self.datum_counts.row(id));
+
+                for (column, (log_likelihood, denominator)) in columns.enumerate() {
+                    let datum_count_in_class = row.map_or(0.0, |row| row[column]);
+
+                    *log_likelihood +=
+                        datum_weight * ((datum_count_in_class + alpha) / denominator).ln();
+                }
+            }
+            Variant::Bernoulli => {
+                let row = id.map(|id| self.datum_counts.document_row(id));
+
+                for (column, (log_likelihood, denominator)) in columns.enumerate() {
+                    let document_count = row.map_or(0.0, |row| row[column]);
+
+                    *log_likelihood += ((document_count + alpha) / denominator).ln();
+                }
+            }
+            Variant::Complement => {
+                let row = id.map(|id| (self.datum_counts.total(id), self.datum_counts.row(id)));
+
+                for (column, (log_likelihood, denominator)) in columns.enumerate() {
+                    let complement_count = row.map_or(0.0, |(total, row)| total - row[column]);
+                    let complement_weight = ((complement_count + alpha) / denominator).ln();
+
+                    *log_likelihood -=
+                        datum_weight * complement_weight / tables.normalizers[column];
+                }
+            }
+        }
+    }
+
+    /// Log probability, per class column, of every vocabulary token outside `present` being
+    /// absent from the input.
+    fn bernoulli_baseline(&self, tables: &ScoringTables, present: &HashSet<usize>) -> Vec<Number> {
+        let alpha = self.smoothing.alpha();
+
+        tables
+            .absent
+            .iter()
+            .zip(tables.denominators.iter())
+            .enumerate()
+            .map(|(column, (&(mut finite, mut infinite), denominator))| {
+                for &id in present.iter() {
+                    let document_count = self.datum_counts.document_row(id)[column];
+                    let log_absence = (1.0 - (document_count + alpha) / denominator).ln();
+
+                    if log_absence == Number::NEG_INFINITY {
+                        infinite -= 1;
+                    } else {
+                        finite -= log_absence;
+                    }
+                }
+
+                if infinite > 0 {
+                    Number::NEG_INFINITY
+                } else {
+                    finite
+                }
+            })
+            .collect()
+    }
+
+    /// Log prior of each class, indexed by class column.
+    fn log_priors(&self, tables: &ScoringTables, priors: &Priors<T>) -> Vec<Number> {
+        match priors {
+            Priors::Fitted => tables.fitted_log_priors.clone(),
+            Priors::Uniform => vec![-(self.classes.len() as Number).ln(); self.classes.len()],
+            Priors::Custom(probabilities) => self
+                .classes
+                .iter()
+                .map(|class| probabilities.get(class).cloned().unwrap_or(0.0).ln())
+                .collect(),
+        }
+    }
+
+    fn tables(&self) -> &ScoringTables {
+        self.tables.get_or_init(|| ScoringTables::new(self))
+    }
+
+    /// Row of `Token::unknown`, if the token type has one and it was trained on.
+    fn unknown_token_id(&self) -> Option<usize> {
+        K::unknown().and_then(|token| self.datum_counts.id(&token))
+    }
+
+    /// Checks that the settings can be scored with. Complement weights are logs of complement
+    /// counts, which are zero for a token seen in only one class unless smoothed.
+    fn check_settings(&self) -> Result<(), String> {
+        self.smoothing.check()?;
+        self.priors.check()?;
+
+        if self.variant == Variant::Complement && self.smoothing.alpha() <= 0.0 {
+            return Err(
+                "the complement variant needs a positive smoothing pseudocount".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn assert_settings(&self) {
+        if let Err(reason) = self.check_settings() {
+            panic!("{}", reason);
+        }
+    }
+
+    /// Resolves an input token's row `id`, if it has one, according to the unknown token policy.
+    /// Without a pseudocount an unknown token with no row of its own has no probability to score,
+    /// so it is skipped.
+    fn lookup(&self, tables: &ScoringTables, id: Option<usize>) -> Lookup {
+        let unknown = match id {
+            Some(id) => return Lookup::Known(id),
+            None => match self.unknown_tokens {
+                UnknownTokens::Ignore => return Lookup::Skipped,
+                UnknownTokens::Smooth => None,
+                UnknownTokens::Token => tables.unknown_token_id,
+            },
+        };
+
+        if unknown.is_none() && self.smoothing.alpha() == 0.0 {
+            Lookup::Skipped
+        } else {
+            Lookup::Unknown(unknown)
+        }
+    }
+}
+
+/// Everything scoring needs that does not depend on the input, with one entry per class column
+/// in each vector, so that scoring an input only touches the rows of its own tokens.
+#[derive(Clone)]
+struct ScoringTables {
+    /// Log priors under `Priors::Fitted`.
+    fitted_log_priors: Vec<Number>,
+    /// Denominators of the smoothed token probabilities: class token totals for the multinomial
+    /// variant, class document totals for Bernoulli and complement token totals for complement.
+    denominators: Vec<Number>,
+    /// Bernoulli log probabilities of every vocabulary token being absent, summed over the finite
+    /// ones, and how many were negative infinity, so that present tokens can be taken back out.
+    absent: Vec<(Number, usize)>,
+    /// Sum of the absolute complement log weights of every vocabulary token.
+    normalizers: Vec<Number>,
+    unknown_token_id: Option<usize>,
+}
+
+impl ScoringTables {
+    fn new<T: Hash + Eq + Clone, K: Token>(
+        classifier: &NaiveBayesClassifier<T, K>,
+    ) -> ScoringTables {
+        let alpha = classifier.smoothing.alpha();
+        let unknown_token_id = classifier.unknown_token_id();
+        let columns = classifier.classes.len();
+        let datum_counts = &classifier.datum_counts;
+
+        // Including a slot for unknown tokens when they are scored.
+        let reserves_unknown_slot = match classifier.unknown_tokens {
+            UnknownTokens::Ignore => false,
+            UnknownTokens::Smooth => true,
+            UnknownTokens::Token => unknown_token_id.is_none(),
+        };
+        let vocabulary_size =
+            datum_counts.len() as Number + if reserves_unknown_slot { 1.0 } else { 0.0 };
+
+        let class_counts = &classifier.class_counts;
+        let document_counts = &classifier.class_document_counts;
+
+        let fitted_log_priors = classifier
+            .classes
+            .iter()
+            .map(|class| match classifier.variant {
+                Variant::Complement => 0.0,
+                Variant::Multinomial | Variant::Bernoulli => {
+                    (document_counts.count(class) / document_counts.total).ln()
+                }
+            })
+            .collect();
+
+        let denominators: Vec<Number> = classifier
+            .classes
+            .iter()
+            .map(|class| match classifier.variant {
+                Variant::Multinomial => class_counts.count(class) + alpha * vocabulary_size,
+                Variant::Bernoulli => document_counts.count(class) + 2.0 * alpha,
+                Variant::Complement => {
+                    class_counts.total - class_counts.count(class) + alpha * vocabulary_size
+                }
+            })
+            .collect();
+
+        let mut absent: Vec<(Number, usize)> = Vec::new();
+        let mut normalizers: Vec<Number> = Vec::new();
+
+        match classifier.variant {
+            Variant::Multinomial => {}
+            Variant::Bernoulli => {
+                absent = vec![(0.0, 0); columns];
+
+                for id in datum_counts.ids() {
+                    let row = datum_counts.document_row(id);
+
+                    for (column, (finite, infinite)) in absent.iter_mut().enumerate() {
+                        let log_absence = (1.0 - (row[column] + alpha) / denominators[column]).ln();
+
+                        if log_absence == Number::NEG_INFINITY {
+                            *infinite += 1;
+                        } else {
+                            *finite += log_absence;
+                        }
+                    }
+                }
+            }
+            Variant::Complement => {
+                normalizers = vec![0.0; columns];
+
+                for id in datum_counts.ids() {
+                    let total = datum_counts.total(id);
+                    let row = datum_counts.row(id);
+
+                    for (column, normalizer) in normalizers.iter_mut().enumerate() {
+                        let complement_count = total - row[column];
+
+                        *normalizer += ((complement_count + alpha) / denominators[column])
+                            .ln()
+                            .abs();
+                    }
+                }
+            }
+        }
+
+        ScoringTables {
+            fitted_log_priors,
+            denominators,
+            absent,
+            normalizers,
+            unknown_token_id,
+        }
+    }
+}
+
+/// Where an input token's counts come from during scoring.
+#[derive(Clone, Copy)]
+enum Lookup {
+    Known(usize),
+    /// An unknown token, scored from the `Token::unknown` row if there is one.
+    Unknown(Option<usize>),
+    Skipped,
+}
+
+impl Lookup {
+    fn id(&self) -> Option<usize> {
+        match *self {
+            Lookup::Known(id) => Some(id),
+            Lookup::Unknown(id) => id,
+            Lookup::Skipped => None,
+        }
+    }
+}
+
+/// Log-likelihood terms of one scoring pass, with one entry per class column in each vector.
+struct Breakdown<K> {
+    tokens: Vec<TokenTerms<K>>,
+    /// Terms that do not belong to any input token, such as the Bernoulli probability of every
+    /// absent vocabulary token.
+    baseline: Vec<Number>,
+    /// Distinct input tokens missing from the vocabulary, whether scored or skipped.
+    unknown: Vec<K>,
+}
+
+struct TokenTerms<K> {
+    datum: K,
+    weight: Number,
+    known: bool,
+    log_likelihoods: Vec<Number>,
+}
+
+impl<K> TokenTerms<K> {
+    fn new(datum: K, weight: Number, lookup: Lookup, log_likelihoods: Vec<Number>) -> Self {
+        TokenTerms {
+            datum,
+            weight,
+            known: matches!(lookup, Lookup::Known(_)),
+            log_likelihoods,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct SavedModel<'a, T: Hash + Eq + Clone + Serialize, K: Token + Serialize> {
+    version: u32,
+    classifier: &'a NaiveBayesClassifier<T, K>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct LoadedModel {
+    version: u32,
+    classifier: serde_json::Value,
+}
+
+#[cfg(feature = "serde")]
+impl<T, K> NaiveBayesClassifier<T, K>
+where
+    T: Hash + Eq + Clone + Serialize + DeserializeOwned,
+    K: Token + Serialize + DeserializeOwned,
+{
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_writer(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<NaiveBayesClassifier<T, K>, Error> {
+        NaiveBayesClassifier::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let saved_model = SavedModel {
+            version: FORMAT_VERSION,
+            classifier: self,
+        };
+
+        Ok(serde_json::to_writer(writer, &saved_model)?)
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<NaiveBayesClassifier<T, K>, Error> {
+        let loaded_model: LoadedModel = serde_json::from_reader(reader)?;
+
+        let classifier: NaiveBayesClassifier<T, K> = match loaded_model.version {
+            1 => {
+                let classifier: ClassifierV1<T, K> =
+                    serde_json::from_value(loaded_model.classifier)?;
+                classifier.into()
+            }
+            2 => {
+                let mut classifier: NaiveBayesClassifier<T, K> =
+                    serde_json::from_value(loaded_model.classifier)?;
+                classifier.estimate_document_counts();
+                classifier
+            }
+            3..=FORMAT_VERSION => serde_json::from_value(loaded_model.classifier)?,
+            found => {
+                return Err(Error::UnsupportedVersion {
+                    found,
+                    expected: FORMAT_VERSION,
+                })
+            }
+        };
+
+        classifier
+            .datum_counts
+            .validate(classifier.classes.len())
+            .map_err(Error::InvalidModel)?;
+        classifier.check_settings().map_err(Error::InvalidModel)?;
+
+        Ok(classifier)
+    }
+}
+
+/// Layout written by format version 1, before token counts were stored densely.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct ClassifierV1<T: Hash + Eq + Clone, K: Token> {
+    classes: Classes<T>,
+    class_counts: Counts<T>,
+    datum_counts: HashMap<K, Counts<T>>,
+    smoothing: Smoothing,
+    unknown_tokens: UnknownTokens,
+    reject_option: RejectOption,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Hash + Eq + Clone, K: Token> From<ClassifierV1<T, K>> for NaiveBayesClassifier<T, K> {
+    fn from(classifier: ClassifierV1<T, K>) -> NaiveBayesClassifier<T, K> {
+        let mut datum_counts = Vocabulary::with_columns(classifier.classes.len());
+
+        for (datum, counts) in classifier.datum_counts {
+            let id = datum_counts.intern(datum);
+
+            for (column, class) in classifier.classes.iter().enumerate() {
+                datum_counts.add(id, column, counts.count(class));
+            }
+        }
+
+        let mut migrated = NaiveBayesClassifier {
+            classes: classifier.classes,
+            class_counts: classifier.class_counts,
+            class_document_counts: Counts::new(),
+            datum_counts,
+            variant: Variant::Multinomial,
+            priors: Priors::Fitted,
+            smoothing: classifier.smoothing,
+            unknown_tokens: classifier.unknown_tokens,
+            reject_option: classifier.reject_option,
+            tables: OnceLock::new(),
+        };
+
+        migrated.estimate_document_counts();
+        migrated
+    }
+}
+
+/// Fails unless `weight` is finite and non-negative.
+fn check_weight(weight: Number) -> Result<(), Error> {
+    if weight.is_finite() && weight >= 0.0 {
+        Ok(())
+    } else {
+        Err(Error::InvalidWeight { weight })
+    }
+}
+
+/// Orders `classes` by descending posterior, keeping training order among ties.
+fn rank<T: Hash + Eq + Clone>(classes: &[T], posteriors: &ProbabilityMap<T>) -> Vec<Prediction<T>> {
+    let mut ranking: Vec<Prediction<T>> = classes
+        .iter()
+        .filter_map(|class| {
+            posteriors.get(class).map(|&confidence| Prediction {
+                class: class.clone(),
+                confidence,
+            })
+        })
+        .collect();
+
+    ranking.sort_by(|a, b| {
+        b.confidence
+            .partial_cmp(&a.confidence)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    ranking
+}
+
+pub fn log_sum_exp<'a, I: IntoIterator<Item = &'a Number>>(values: I) -> Number {
+    let values: Vec<Number> = values.into_iter().cloned().collect();
+    let max = values
+        .iter()
+        .cloned()
+        .fold(Number::NEG_INFINITY, Number::max);
+
+    if max == Number::NEG_INFINITY || max == Number::INFINITY {
+        return max;
+    }
+
+    max + values
+        .iter()
+        .map(|value| (value - max).exp())
+        .sum::<Number>()
+        .ln()
+}
+
+/// Turns log scores into posteriors summing to one. Returns an empty map when the scores give
+/// no usable evidence: every class is impossible, or some score is infinite or NaN.
+pub fn normalize<T: Hash + Eq + Clone>(log_scores: &ProbabilityMap<T>) -> ProbabilityMap<T> {
+    let log_evidence = log_sum_exp(log_scores.values());
+
+    if !log_evidence.is_finite() {
+        return ProbabilityMap::new();
+    }
+
+    log_scores
+        .iter()
+        .map(|(class, log_score)| (class.clone(), (log_score - log_evidence).exp()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn trained(documents: &[(u32, &str)]) -> NaiveBayesClassifier<u32> {
+        let mut classifier = NaiveBayesClassifier::new();
+
+        for &(class, text) in documents {
+            classifier.train(class, Words::new(text));
+        }
+
+        classifier
+    }
+
+    pub(crate) fn assert_close(left: &ProbabilityMap<u32>, right: &ProbabilityMap<u32>) {
+        assert_eq!(left.len(), right.len(), "{:?} != {:?}", left, right);
+
+        for (class, value) in left.iter() {
+            let other = right[class];
+
+            assert!(
+                value == &other || (value - other).abs() < 1e-9,
+                "{:?} != {:?}",
+                left,
+                right
+            );
+        }
+    }
+
+    #[test]
+    fn long_documents_do_not_underflow() {
+        // Each class scores the 2,000 tokens far below the smallest f64, so only log space can
+        // tell them apart: class 1 wins by ln 2 + 2000 ln((3/7) / (2/5)).
+        let classifier = trained(&[(1, "a b"), (1, "a b"), (2, "a b")]);
+        let text = vec!["a b"; 1_000].join(" ");
+        let posteriors = classifier.analyze(Words::new(&text));
+        let margin = Number::ln(2.0) + 2_000.0 * Number::ln(15.0 / 14.0);
+
+        assert!(posteriors.values().all(|posterior| posterior.is_finite()));
+        assert!((posteriors[&1] + posteriors[&2] - 1.0).abs() < 1e-12);
+        assert!(posteriors[&2] > 0.0);
+        assert!((posteriors[&2] / (-margin).exp() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn predictions_rank_classes_by_posterior() {
+        let classifier = trained(&[(1, "c"), (2, "b c"), (3, "a a b")]);
+        let posteriors = classifier.analyze(Words::new("a b"));
+        let ranking = classifier.predict_top_k(Words::new("a b"), 5);
+
+        assert_eq!(
+            ranking.iter().map(|p| p.class).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+        for prediction in ranking.iter() {
+            assert_eq!(prediction.confidence, posteriors[&prediction.class]);
+        }
+
+        assert_eq!(classifier.predict_top_k(Words::new("a b"), 2), ranking[..2]);
+        assert!(classifier.predict_top_k(Words::new("a b"), 0).is_empty());
+        assert_eq!(
+            classifier.predict(Words::new("a b")).as_ref(),
+            ranking.first()
+        );
+        assert_eq!(
+            classifier.predict_known(Words::new("a b")).as_ref(),
+            ranking.first()
+        );
+    }
+
+    #[test]
+    fn predictions_keep_training_order_among_ties() {
+        let classes = |classifier: &NaiveBayesClassifier<u32>| {
+            classifier
+                .predict_top_k(Words::new("z"), 2)
+                .into_iter()
+                .map(|p| p.class)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(classes(&trained(&[(1, "a"), (2, "b")])), vec![1, 2]);
+        assert_eq!(classes(&trained(&[(2, "b"), (1, "a")])), vec![2, 1]);
+    }
+
+    #[test]
+    fn predicting_known_tokens_only_refuses_unseen_input() {
+        let classifier = trained(&[(1, "a"), (2, "b b")]);
+
+        assert!(classifier.predict(Words::new("y z")).is_some());
+        assert_eq!(classifier.predict_known(Words::new("y z")), None);
+        assert_eq!(classifier.predict_known(Words::new("")), None);
+        assert_eq!(
+            classifier.predict_known(Words::new("z a")).map(|p| p.class),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn borrowed_tokenization_trains_and_scores_like_owned() {
+        struct Owned<'a>(&'a str);
+
+        impl Tokenize for Owned<'_> {
+            fn tokenize(&self) -> Vec<DataType> {
+                self.0.split(' ').map(str::to_string).collect()
+            }
+        }
+
+        struct Borrowed<'a>(&'a str);
+
+        impl TokenizeBorrowed for Borrowed<'_> {
+            type Borrowed = str;
+
+            fn tokenize_borrowed<'a>(&'a self) -> impl Iterator<Item = Cow<'a, str>>
+            where
+                str: 'a,
+            {
+                self.0.split(' ').map(Cow::Borrowed)
+            }
+        }
+
+        let documents = [(1, "a b a"), (2, "b c"), (2, "c d"), (1, "a")];
+        let mut owned: NaiveBayesClassifier<u32> = NaiveBayesClassifier::new();
+        let mut borrowed: NaiveBayesClassifier<u32> = NaiveBayesClassifier::new();
+
+        for &(class, text) in documents.iter() {
+            owned.train(class, Owned(text));
+            borrowed.train(class, Borrowed(text));
+        }
+        owned.untrain(2, Owned("c d")).unwrap();
+        borrowed.untrain(2, Borrowed("c d")).unwrap();
+
+        assert_eq!(borrowed.vocabulary_size(), owned.vocabulary_size());
+        for text in ["a", "b c e", "d a a"] {
+            assert_close(
+                &borrowed.log_scores(Borrowed(text)),
+                &owned.log_scores(Owned(text)),
+            );
+            assert_close(
+                &owned.log_scores(Borrowed(text)),
+                &owned.log_scores(Owned(text)),
+            );
+        }
+    }
+
+    #[test]
+    fn string_labels_can_be_read_at_runtime() {
+        let config = "spam: buy now buy\nham: see you soon\nspam: now free";
+        let mut classifier = NaiveBayesClassifier::with_string_labels();
+
+        for line in config.lines() {
+            let (label, text) = line.split_once(": ").unwrap();
+            classifier.train(label.to_string(), Words::new(text));
+        }
+
+        assert_eq!(
+            classifier.classes(),
+            &["spam".to_string(), "ham".to_string()]
+        );
+        assert_eq!(classifier.class_document_count(&"spam".to_string()), 2.0);
+        assert_eq!(
+            classifier.predict(Words::new("buy free")).unwrap().class,
+            "spam"
+        );
+        assert_eq!(
+            classifier.predict(Words::new("see you")).unwrap().class,
+            "ham"
+        );
+    }
+
+    #[test]
+    fn untraining_a_class_in_parts_removes_its_documents() {
+        let mut classifier = trained(&[(1, "a b"), (2, "c")]);
+        classifier.untrain(1, Words::new("a")).unwrap();
+
+        assert_eq!(classifier.class_document_count(&1), 1.0);
+        assert_close(
+            &classifier.log_scores(Words::new("b b b")),
+            &trained(&[(1, "b"), (2, "c")]).log_scores(Words::new("b b b")),
+        );
+        assert!(classifier.analyze(Words::new("b b b"))[&1] > 0.5);
+
+        classifier.untrain(1, Words::new("b")).unwrap();
+
+        assert_eq!(classifier.classes(), &[2]);
+        assert_eq!(classifier.class_document_count(&2), 1.0);
+        assert_close(
+            &classifier.log_scores(Words::new("c")),
+            &trained(&[(2, "c")]).log_scores(Words::new("c")),
+        );
+    }
+
+    #[test]
+    fn untraining_a_class_at_once_removes_all_its_documents() {
+        let mut classifier = trained(&[(1, "a"), (1, "b"), (2, "c")]);
+        classifier.untrain(1, Words::new("a b")).unwrap();
+
+        assert_eq!(classifier.class_document_count(&1), 0.0);
+        assert_close(
+            &classifier.log_scores(Words::new("c")),
+            &trained(&[(2, "c")]).log_scores(Words::new("c")),
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips_tokens_that_are_not_strings() {
+        struct Bytes(&'static [&'static [u8]]);
+
+        impl Tokenize<Vec<u8>> for Bytes {
+            fn tokenize(&self) -> Vec<Vec<u8>> {
+                self.0.iter().map(|datum| datum.to_vec()).collect()
+            }
+        }
+
+        let mut classifier: NaiveBayesClassifier<u32, Vec<u8>> = NaiveBayesClassifier::new();
+        classifier.train(1, Bytes(&[&[0, 1], &[2]]));
+        classifier.train(2, Bytes(&[&[2], &[3]]));
+
+        let mut saved = Vec::new();
+        classifier.to_writer(&mut saved).unwrap();
+        let loaded: NaiveBayesClassifier<u32, Vec<u8>> =
+            NaiveBayesClassifier::from_reader(&saved[..]).unwrap();
+
+        assert_close(
+            &loaded.log_scores(Bytes(&[&[0, 1], &[3]])),
+            &classifier.log_scores(Bytes(&[&[0, 1], &[3]])),
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_loads_the_interned_layout_of_version_4() {
+        let saved = r#"{"version":4,"classifier":{"classes":[1,2],"class_counts":{"total":5.0,"per_class":{"1":3.0,"2":2.0}},"class_document_counts":{"total":2.0,"per_class":{"2":1.0,"1":1.0}},"datum_counts":{"ids":{"b":1,"c":2,"a":0},"free_rows":[],"totals":[2.0,2.0,1.0],"counts":[2.0,0.0,1.0,1.0,0.0,1.0],"documents":[1.0,0.0,1.0,1.0,0.0,1.0],"columns":2},"variant":"Multinomial","priors":"Fitted","smoothing":"Laplace","unknown_tokens":"Smooth","reject_option":{"min_confidence":0.0,"min_margin":0.0}}}"#;
+        let loaded: NaiveBayesClassifier<u32> =
+            NaiveBayesClassifier::from_reader(saved.as_bytes()).unwrap();
+
+        assert_close(
+            &loaded.log_scores(Words::new("a c d")),
+            &trained(&[(1, "a b a"), (2, "b c")]).log_scores(Words::new("a c d")),
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_rejects_a_vocabulary_whose_layout_does_not_add_up() {
+        let saved = r#"{"version":4,"classifier":{"classes":[1,2],"class_counts":{"total":5.0,"per_class":{"1":3.0,"2":2.0}},"class_document_counts":{"total":2.0,"per_class":{"2":1.0,"1":1.0}},"datum_counts":{"ids":{"b":1,"c":2,"a":0},"free_rows":[],"totals":[2.0,2.0,1.0],"counts":[1.0],"documents":[1.0,0.0,1.0,1.0,0.0,1.0],"columns":2},"variant":"Multinomial","priors":"Fitted","smoothing":"Laplace","unknown_tokens":"Smooth","reject_option":{"min_confidence":0.0,"min_margin":0.0}}}"#;
+        let loaded: Result<NaiveBayesClassifier<u32>, Error> =
+            NaiveBayesClassifier::from_reader(saved.as_bytes());
+
+        assert!(matches!(loaded, Err(Error::InvalidModel(_))));
+
+        let overlapping = saved
+            .replace(r#""counts":[1.0]"#, r#""counts":[2.0,0.0,1.0,1.0,0.0,1.0]"#)
+            .replace(r#""free_rows":[]"#, r#""free_rows":[1]"#);
+        let loaded: Result<NaiveBayesClassifier<u32>, Error> =
+            NaiveBayesClassifier::from_reader(overlapping.as_bytes());
+
+        assert!(matches!(loaded, Err(Error::InvalidModel(_))));
+    }
+
+    #[test]
+    fn a_document_no_class_can_explain_is_unknown() {
+        let mut classifier = NaiveBayesClassifier::new().with_smoothing(Smoothing::None);
+        classifier.train(1, Words::new("a"));
+        classifier.train(2, Words::new("b"));
+
+        assert!(classifier.analyze(Words::new("a b")).is_empty());
+        assert_eq!(classifier.classify(Words::new("a b")), Outcome::Unknown);
+        assert_eq!(
+            classifier.classify(Words::new("a")),
+            Outcome::Class(Prediction {
+                class: 1,
+                confidence: 1.0
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_tokens_are_ignored_without_smoothing() {
+        let mut classifier = NaiveBayesClassifier::new().with_smoothing(Smoothing::None);
+        classifier.train(1, Words::new("a b"));
+        classifier.train(2, Words::new("a c"));
+
+        assert_close(
+            &classifier.log_scores(Words::new("a z")),
+            &classifier.log_scores(Words::new("a")),
+        );
+        assert!(classifier
+            .log_scores(Words::new("a z"))
+            .values()
+            .all(|log_score| log_score.is_finite()));
+    }
+
+    #[test]
+    #[should_panic(expected = "positive smoothing pseudocount")]
+    fn the_complement_variant_needs_smoothing() {
+        let _: NaiveBayesClassifier<u32> = NaiveBayesClassifier::new()
+            .with_variant(Variant::Complement)
+            .with_smoothing(Smoothing::None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_rejects_the_complement_variant_without_smoothing() {
+        let mut saved = Vec::new();
+        trained(&[(1, "a"), (2, "b")])
+            .with_variant(Variant::Complement)
+            .to_writer(&mut saved)
+            .unwrap();
+        let saved = String::from_utf8(saved)
+            .unwrap()
+            .replace(r#""smoothing":"Laplace""#, r#""smoothing":"None""#);
+        let loaded: Result<NaiveBayesClassifier<u32>, Error> =
+            NaiveBayesClassifier::from_reader(saved.as_bytes());
+
+        assert!(matches!(loaded, Err(Error::InvalidModel(_))));
+    }
+
+    #[test]
+    fn training_rejects_weights_that_are_negative_or_not_finite() {
+        struct Weighted(Number);
+
+        impl Tokenize for Weighted {
+            fn tokenize(&self) -> Vec<DataType> {
+                vec!["a".to_string(), "b".to_string()]
+            }
+
+            fn weighted_tokens(&self) -> Vec<(DataType, Number)> {
+                vec![("a".to_string(), 1.0), ("b".to_string(), self.0)]
+            }
+        }
+
+        let mut classifier = trained(&[(1, "a"), (2, "b")]);
+        let before = classifier.log_scores(Words::new("a b"));
+
+        for weight in [-1.0, Number::NAN, Number::INFINITY] {
+            assert!(matches!(
+                classifier.train_weighted(1, Words::new("a b"), weight),
+                Err(Error::InvalidWeight { .. })
+            ));
+            assert!(matches!(
+                classifier.train_weighted(1, Weighted(weight), 1.0),
+                Err(Error::InvalidWeight { .. })
+            ));
+            assert!(matches!(
+                classifier.untrain_weighted(1, Words::new("a"), weight),
+                Err(Error::InvalidWeight { .. })
+            ));
+        }
+
+        assert_close(&classifier.log_scores(Words::new("a b")), &before);
+    }
+
+    #[test]
+    fn training_with_no_weight_leaves_the_model_unchanged() {
+        struct Unweighted;
+
+        impl Tokenize for Unweighted {
+            fn tokenize(&self) -> Vec<DataType> {
+                vec!["a".to_string(), "c".to_string()]
+            }
+
+            fn weighted_tokens(&self) -> Vec<(DataType, Number)> {
+                vec![("a".to_string(), 1.0), ("c".to_string(), 0.0)]
+            }
+        }
+
+        let mut classifier = trained(&[(1, "a"), (2, "b")]);
+        let before = classifier.analyze(Words::new("a b"));
+
+        classifier
+            .train_weighted(3, Words::new("a b"), 0.0)
+            .unwrap();
+        classifier
+            .untrain_weighted(3, Words::new("a b"), 0.0)
+            .unwrap();
+
+        assert_eq!(classifier.classes(), &[1, 2]);
+        assert_eq!(classifier.vocabulary_size(), 2);
+        assert_close(&classifier.analyze(Words::new("a b")), &before);
+
+        classifier.train_weighted(1, Unweighted, 1.0).unwrap();
+
+        assert_eq!(classifier.vocabulary_size(), 2);
+        assert_eq!(classifier.class_token_count(&1), 2.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a count")]
+    fn smoothing_rejects_a_negative_pseudocount() {
+        let _: NaiveBayesClassifier<u32> =
+            NaiveBayesClassifier::new().with_smoothing(Smoothing::Lidstone(-0.5));
+    }
+
+    #[test]
+    fn merging_matches_training_sequentially() {
+        let documents = [(1, "a b a"), (2, "b c"), (3, "c d"), (1, "d a"), (2, "e")];
+
+        for variant in [
+            Variant::Multinomial,
+            Variant::Bernoulli,
+            Variant::Complement,
+        ] {
+            let sequential = trained(&documents).with_variant(variant);
+            let mut merged = trained(&documents[..2]).with_variant(variant);
+            merged.merge(&trained(&documents[2..])).unwrap();
+
+            assert_eq!(merged.class_document_count(&1), 2.0);
+
+            for text in ["a", "b c f", "d e a a"] {
+                assert_close(
+                    &merged.log_scores(Words::new(text)),
+                    &sequential.log_scores(Words::new(text)),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn merging_rejects_models_configured_differently() {
+        let mut merged = trained(&[(1, "a")]);
+        let before = merged.log_scores(Words::new("a b"));
+
+        assert!(matches!(
+            merged.merge(&trained(&[(2, "b")]).with_smoothing(Smoothing::Lidstone(0.5))),
+            Err(Error::SmoothingMismatch { .. })
+        ));
+        assert!(matches!(
+            merged.merge(&trained(&[(2, "b")]).with_unknown_tokens(UnknownTokens::Ignore)),
+            Err(Error::UnknownTokensMismatch {
+                expected: UnknownTokens::Smooth,
+                found: UnknownTokens::Ignore,
+            })
+        ));
+        assert_close(&merged.log_scores(Words::new("a b")), &before);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips_every_variant() {
+        for variant in [
+            Variant::Multinomial,
+            Variant::Bernoulli,
+            Variant::Complement,
+        ] {
+            let classifier = trained(&[(1, "a b a"), (2, "b c"), (2, "c")])
+                .with_variant(variant)
+                .with_unknown_tokens(UnknownTokens::Ignore);
+            let mut saved = Vec::new();
+            classifier.to_writer(&mut saved).unwrap();
+            let loaded: NaiveBayesClassifier<u32> =
+                NaiveBayesClassifier::from_reader(&saved[..]).unwrap();
+
+            assert_eq!(loaded.class_document_count(&2), 2.0);
+            assert_close(
+                &loaded.log_scores(Words::new("a c d")),
+                &classifier.log_scores(Words::new("a c d")),
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_migrates_versions_1_and_2() {
+        let version_1 = r#"{"version":1,"classifier":{"classes":[1,2],"class_counts":{"total":5.0,"per_class":{"2":2.0,"1":3.0}},"datum_counts":{"a":{"total":2.0,"per_class":{"1":2.0}},"b":{"total":2.0,"per_class":{"1":1.0,"2":1.0}},"c":{"total":1.0,"per_class":{"2":1.0}}},"smoothing":"Laplace","unknown_tokens":"Smooth","reject_option":{"min_confidence":0.0,"min_margin":0.0}}}"#;
+        let version_2 = r#"{"version":2,"classifier":{"classes":[1,2],"class_counts":{"total":5.0,"per_class":{"1":3.0,"2":2.0}},"datum_counts":{"ids":{"a":0,"b":1,"c":2},"free_rows":[],"totals":[2.0,2.0,1.0],"counts":[2.0,0.0,1.0,1.0,0.0,1.0],"columns":2},"smoothing":"Laplace","unknown_tokens":"Smooth","reject_option":{"min_confidence":0.0,"min_margin":0.0}}}"#;
+        let expected = trained(&[(1, "a b a"), (2, "b c")]).with_priors(Priors::Uniform);
+
+        // Neither version tracked documents, so they are estimated from the token counts.
+        for saved in [version_1, version_2] {
+            let loaded: NaiveBayesClassifier<u32> =
+                NaiveBayesClassifier::from_reader(saved.as_bytes())
+                    .unwrap()
+                    .with_priors(Priors::Uniform);
+
+            assert_eq!(loaded.class_document_count(&1), 3.0);
+            assert_close(
+                &loaded.log_scores(Words::new("a c d")),
+                &expected.log_scores(Words::new("a c d")),
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips_labels_that_are_not_strings() {
+        let mut classifier: NaiveBayesClassifier<(u8, u8)> = NaiveBayesClassifier::new();
+        classifier.train((1, 0), Words::new("a b a"));
+        classifier.train((2, 5), Words::new("b c"));
+        let mut probabilities = ProbabilityMap::new();
+        probabilities.insert((1, 0), 0.25);
+        probabilities.insert((2, 5), 0.75);
+        let classifier = classifier.with_priors(Priors::Custom(probabilities));
+
+        let mut saved = Vec::new();
+        classifier.to_writer(&mut saved).unwrap();
+        let loaded: NaiveBayesClassifier<(u8, u8)> =
+            NaiveBayesClassifier::from_reader(&saved[..]).unwrap();
+
+        assert_eq!(loaded.class_document_count(&(2, 5)), 1.0);
+        assert_eq!(
+            loaded.log_scores(Words::new("a c d")),
+            classifier.log_scores(Words::new("a c d"))
+        );
+    }
+
+    #[test]
+    fn fitted_priors_count_documents_rather_than_tokens() {
+        let classifier = trained(&[(1, "a a a a"), (2, "b"), (2, "c")]);
+        let log_scores = classifier.log_scores(Words::new(""));
+
+        assert!((log_scores[&1] - (1.0 as Number / 3.0).ln()).abs() < 1e-12);
+        assert!((log_scores[&2] - (2.0 as Number / 3.0).ln()).abs() < 1e-12);
+
+        let complement = trained(&[(1, "a a a a"), (2, "b"), (2, "c")])
+            .with_variant(Variant::Complement)
+            .log_scores(Words::new(""));
+
+        assert_eq!(complement[&1], 0.0);
+        assert_eq!(complement[&2], 0.0);
+    }
+
+    #[test]
+    fn uniform_and_custom_priors_replace_the_fitted_ones() {
+        let mut probabilities = ProbabilityMap::new();
+        probabilities.insert(1, 0.9);
+        let classifier =
+            trained(&[(1, "a"), (2, "b"), (2, "c")]).with_priors(Priors::Custom(probabilities));
+        let log_scores = classifier.log_scores(Words::new(""));
+
+        assert!((log_scores[&1] - (0.9 as Number).ln()).abs() < 1e-12);
+        assert_eq!(log_scores[&2], Number::NEG_INFINITY);
+
+        let uniform = classifier.log_scores_with_priors(Words::new(""), &Priors::Uniform);
+
+        assert!((uniform[&1] - (0.5 as Number).ln()).abs() < 1e-12);
+        assert!((uniform[&2] - (0.5 as Number).ln()).abs() < 1e-12);
+        assert!(
+            (classifier.analyze_with_priors(Words::new(""), &Priors::Fitted)[&2] - 2.0 / 3.0).abs()
+                < 1e-12
+        );
+        assert_eq!(classifier.analyze(Words::new(""))[&1], 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a probability")]
+    fn custom_priors_must_be_probabilities() {
+        let mut probabilities = ProbabilityMap::new();
+        probabilities.insert(1, -0.5);
+        let _ = trained(&[(1, "a"), (2, "b")]).with_priors(Priors::Custom(probabilities));
+    }
 
-        probability_map
+    #[test]
+    #[should_panic(expected = "is not a probability")]
+    fn custom_priors_for_one_call_must_be_probabilities() {
+        let mut probabilities = ProbabilityMap::new();
+        probabilities.insert(1, Number::NAN);
+        trained(&[(1, "a"), (2, "b")])
+            .analyze_with_priors(Words::new("a"), &Priors::Custom(probabilities));
     }
 }
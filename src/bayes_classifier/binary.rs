@@ -0,0 +1,437 @@
+use super::{
+    Classes, Counts, Error, NaiveBayesClassifier, Number, Priors, ProbabilityMap, RejectOption,
+    Smoothing, Token, UnknownTokens, Variant, Vocabulary,
+};
+use std::convert::TryInto;
+use std::fmt::Display;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const MAGIC: &[u8; 4] = b"RMNB";
+
+/// Version written into saved binary models. Bump when the binary layout changes.
+pub const BINARY_FORMAT_VERSION: u32 = 3;
+
+// Layout, all integers and floats little-endian:
+//
+//   magic "RMNB", version u32
+//   smoothing tag u8, smoothing alpha f64
+//   unknown tokens tag u8
+//   variant tag u8 (since version 2)
+//   reject option min_confidence f64, min_margin f64
+//   class count u32, then per class: label string, token count f64,
+//     document count f64 (since version 2)
+//   priors tag u8, then for custom priors a count u32 and per class: label string,
+//     probability f64 (since version 3)
+//   token count u32, then per token: token string
+//   token count x class count f64 matrix of occurrences, one row per token in dictionary order
+//   token count x class count f64 matrix of documents (since version 2)
+//
+// Strings are a u32 byte length followed by UTF-8 bytes. Labels and tokens are written with
+// `Display` and read back with `FromStr`. Version 1 models are read with their document counts
+// estimated from the occurrence counts.
+impl<T, K> NaiveBayesClassifier<T, K>
+where
+    T: Hash + Eq + Clone + Display + FromStr,
+    K: Token + Display + FromStr,
+{
+    pub fn save_binary<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_binary(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load_binary<P: AsRef<Path>>(path: P) -> Result<NaiveBayesClassifier<T, K>, Error> {
+        NaiveBayesClassifier::read_binary(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_all(MAGIC)?;
+        write_u32(&mut writer, BINARY_FORMAT_VERSION)?;
+
+        let smoothing_tag = match self.smoothing {
+            Smoothing::None => 0,
+            Smoothing::Laplace => 1,
+            Smoothing::Lidstone(_) => 2,
+        };
+        writer.write_all(&[smoothing_tag])?;
+        write_f64(&mut writer, self.smoothing.alpha())?;
+
+        let unknown_tokens_tag = match self.unknown_tokens {
+            UnknownTokens::Ignore => 0,
+            UnknownTokens::Smooth => 1,
+            UnknownTokens::Token => 2,
+        };
+        writer.write_all(&[unknown_tokens_tag])?;
+
+        let variant_tag = match self.variant {
+            Variant::Multinomial => 0,
+            Variant::Bernoulli => 1,
+            Variant::Complement => 2,
+        };
+        writer.write_all(&[variant_tag])?;
+
+        write_f64(&mut writer, self.reject_option.min_confidence)?;
+        write_f64(&mut writer, self.reject_option.min_margin)?;
+
+        write_len(&mut writer, self.classes.len())?;
+        for class in self.classes.iter() {
+            write_string(&mut writer, &class.to_string())?;
+            write_f64(&mut writer, self.class_counts.count(class))?;
+            write_f64(&mut writer, self.class_document_counts.count(class))?;
+        }
+
+        match &self.priors {
+            Priors::Fitted => writer.write_all(&[0])?,
+            Priors::Uniform => writer.write_all(&[1])?,
+            Priors::Custom(probabilities) => {
+                writer.write_all(&[2])?;
+                write_len(&mut writer, probabilities.len())?;
+
+                for (class, probability) in probabilities.iter() {
+                    write_string(&mut writer, &class.to_string())?;
+                    write_f64(&mut writer, *probability)?;
+                }
+            }
+        }
+
+        let entries = self.datum_counts.entries();
+
+        write_len(&mut writer, entries.len())?;
+        for (datum, _) in entries.iter() {
+            write_string(&mut writer, &datum.to_string())?;
+        }
+
+        let mut matrix: Vec<u8> = Vec::with_capacity(entries.len() * self.classes.len() * 8);
+        for (_, id) in entries.iter() {
+            for count in self.datum_counts.row(*id) {
+                matrix.extend_from_slice(&count.to_le_bytes());
+            }
+        }
+        writer.write_all(&matrix)?;
+
+        matrix.clear();
+        for (_, id) in entries.iter() {
+            for count in self.datum_counts.document_row(*id) {
+                matrix.extend_from_slice(&count.to_le_bytes());
+            }
+        }
+        writer.write_all(&matrix)?;
+
+        Ok(())
+    }
+
+    pub fn read_binary<R: Read>(mut reader: R) -> Result<NaiveBayesClassifier<T, K>, Error> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(invalid("missing binary model header".to_string()));
+        }
+
+        let version = read_u32(&mut reader)?;
+
+        if version == 0 || version > BINARY_FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion {
+                found: version,
+                expected: BINARY_FORMAT_VERSION,
+            });
+        }
+
+        let smoothing_tag = read_u8(&mut reader)?;
+        let alpha = read_f64(&mut reader)?;
+        let smoothing = match smoothing_tag {
+            0 => Smoothing::None,
+            1 => Smoothing::Laplace,
+            2 => Smoothing::Lidstone(alpha),
+            tag => return Err(invalid(format!("unknown smoothing tag {}", tag))),
+        };
+
+        let unknown_tokens = match read_u8(&mut reader)? {
+            0 => UnknownTokens::Ignore,
+            1 => UnknownTokens::Smooth,
+            2 => UnknownTokens::Token,
+            tag => return Err(invalid(format!("unknown token policy tag {}", tag))),
+        };
+
+        let variant = if version == 1 {
+            Variant::Multinomial
+        } else {
+            match read_u8(&mut reader)? {
+                0 => Variant::Multinomial,
+                1 => Variant::Bernoulli,
+                2 => Variant::Complement,
+                tag => return Err(invalid(format!("unknown variant tag {}", tag))),
+            }
+        };
+
+        let reject_option = RejectOption {
+            min_confidence: read_f64(&mut reader)?,
+            min_margin: read_f64(&mut reader)?,
+        };
+
+        let class_count = read_u32(&mut reader)? as usize;
+        let mut classes: Classes<T> = Classes::new();
+        let mut class_counts: Counts<T> = Counts::new();
+        let mut class_document_counts: Counts<T> = Counts::new();
+
+        for _ in 0..class_count {
+            let class: T = read_parsed(&mut reader, "class label")?;
+            let count = read_f64(&mut reader)?;
+
+            if !classes.push(class.clone()) {
+                return Err(invalid("duplicate class label".to_string()));
+            }
+
+            class_counts.total += count;
+            class_counts.per_class.insert(class.clone(), count);
+
+            if version > 1 {
+                let document_count = read_f64(&mut reader)?;
+
+                class_document_counts.total += document_count;
+                class_document_counts
+                    .per_class
+                    .insert(class, document_count);
+            }
+        }
+
+        let priors = if version < 3 {
+            Priors::Fitted
+        } else {
+            match read_u8(&mut reader)? {
+                0 => Priors::Fitted,
+                1 => Priors::Uniform,
+                2 => {
+                    let mut probabilities: ProbabilityMap<T> = ProbabilityMap::new();
+
+                    for _ in 0..read_u32(&mut reader)? {
+                        let class = read_parsed(&mut reader, "class label")?;
+                        probabilities.insert(class, read_f64(&mut reader)?);
+                    }
+
+                    Priors::Custom(probabilities)
+                }
+                tag => return Err(invalid(format!("unknown priors tag {}", tag))),
+            }
+        };
+
+        let datum_count = read_u32(&mut reader)? as usize;
+        let mut datum_counts = Vocabulary::with_columns(class_count);
+        let mut ids: Vec<usize> = Vec::new();
+
+        for _ in 0..datum_count {
+            let datum: K = read_parsed(&mut reader, "token")?;
+
+            if datum_counts.contains(&datum) {
+                return Err(invalid(format!("duplicate token {:?}", datum)));
+            }
+
+            ids.push(datum_counts.intern(datum));
+        }
+
+        let counts = read_matrix(&mut reader, datum_count * class_count)?;
+
+        for (row, id) in ids.iter().enumerate() {
+            for column in 0..class_count {
+                datum_counts.add(*id, column, counts[row * class_count + column]);
+            }
+        }
+
+        if version > 1 {
+            let documents = read_matrix(&mut reader, datum_count * class_count)?;
+
+            for (row, id) in ids.iter().enumerate() {
+                for column in 0..class_count {
+                    datum_counts.add_documents(*id, column, documents[row * class_count + column]);
+                }
+            }
+        }
+
+        let mut classifier = NaiveBayesClassifier {
+            classes,
+            class_counts,
+            class_document_counts,
+            datum_counts,
+            variant,
+            priors,
+            smoothing,
+            unknown_tokens,
+            reject_option,
+            tables: OnceLock::new(),
+        };
+
+        if version == 1 {
+            classifier.estimate_document_counts();
+        }
+
+        classifier.check_settings().map_err(invalid)?;
+
+        Ok(classifier)
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidModel(reason)
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> Result<(), Error> {
+    Ok(writer.write_all(&value.to_le_bytes())?)
+}
+
+fn write_len<W: Write>(writer: &mut W, len: usize) -> Result<(), Error> {
+    if len > u32::MAX as usize {
+        return Err(invalid(format!(
+            "{} entries do not fit the binary format",
+            len
+        )));
+    }
+
+    write_u32(writer, len as u32)
+}
+
+fn write_f64<W: Write>(writer: &mut W, value: Number) -> Result<(), Error> {
+    Ok(writer.write_all(&value.to_le_bytes())?)
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> Result<(), Error> {
+    write_len(writer, value.len())?;
+    Ok(writer.write_all(value.as_bytes())?)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, Error> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, Error> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> Result<Number, Error> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(Number::from_le_bytes(bytes))
+}
+
+/// Reads exactly `len` bytes without trusting `len` enough to allocate it up front.
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != len {
+        return Err(invalid("unexpected end of model".to_string()));
+    }
+
+    Ok(bytes)
+}
+
+fn read_matrix<R: Read>(reader: &mut R, len: usize) -> Result<Vec<Number>, Error> {
+    Ok(read_bytes(reader, len * 8)?
+        .chunks_exact(8)
+        .map(|bytes| Number::from_le_bytes(bytes.try_into().expect("chunks are 8 bytes")))
+        .collect())
+}
+
+fn read_parsed<T: FromStr, R: Read>(reader: &mut R, what: &str) -> Result<T, Error> {
+    let text = read_string(reader)?;
+
+    text.parse()
+        .map_err(|_| invalid(format!("cannot parse {} {:?}", what, text)))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, Error> {
+    let len = read_u32(reader)? as usize;
+
+    String::from_utf8(read_bytes(reader, len)?)
+        .map_err(|_| invalid("string is not valid UTF-8".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{assert_close, trained};
+    use super::super::Words;
+    use super::*;
+
+    #[test]
+    fn binary_rejects_a_negative_pseudocount() {
+        let mut saved = Vec::new();
+        trained(&[(1, "a"), (2, "b")])
+            .with_smoothing(Smoothing::Lidstone(0.5))
+            .write_binary(&mut saved)
+            .unwrap();
+        // The pseudocount follows the magic, the version and the smoothing tag.
+        saved[9..17].copy_from_slice(&(-0.5 as Number).to_le_bytes());
+        let loaded: Result<NaiveBayesClassifier<u32>, Error> =
+            NaiveBayesClassifier::read_binary(&saved[..]);
+
+        assert!(matches!(loaded, Err(Error::InvalidModel(_))));
+    }
+
+    #[test]
+    fn binary_round_trips_every_variant() {
+        for variant in [
+            Variant::Multinomial,
+            Variant::Bernoulli,
+            Variant::Complement,
+        ] {
+            let classifier = trained(&[(1, "a b a"), (2, "b c"), (2, "c")])
+                .with_variant(variant)
+                .with_priors(Priors::Uniform);
+            let mut saved = Vec::new();
+            classifier.write_binary(&mut saved).unwrap();
+            let loaded: NaiveBayesClassifier<u32> =
+                NaiveBayesClassifier::read_binary(&saved[..]).unwrap();
+
+            assert_eq!(loaded.class_document_count(&2), 2.0);
+            assert_close(
+                &loaded.log_scores(Words::new("a c d")),
+                &classifier.log_scores(Words::new("a c d")),
+            );
+        }
+    }
+
+    #[test]
+    fn binary_migrates_versions_1_and_2() {
+        let version_1: &[u8] = &[
+            82, 77, 78, 66, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 240, 63, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 49, 0, 0, 0, 0, 0, 0, 8, 64, 1, 0, 0, 0,
+            50, 0, 0, 0, 0, 0, 0, 0, 64, 3, 0, 0, 0, 1, 0, 0, 0, 97, 1, 0, 0, 0, 98, 1, 0, 0, 0,
+            99, 0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0,
+            0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 240, 63,
+        ];
+        let version_2: &[u8] = &[
+            82, 77, 78, 66, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 240, 63, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 49, 0, 0, 0, 0, 0, 0, 8, 64, 0, 0, 0,
+            0, 0, 0, 240, 63, 1, 0, 0, 0, 50, 0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 240, 63,
+            3, 0, 0, 0, 1, 0, 0, 0, 97, 1, 0, 0, 0, 98, 1, 0, 0, 0, 99, 0, 0, 0, 0, 0, 0, 0, 64, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 240, 63, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 240, 63,
+        ];
+        let expected = trained(&[(1, "a b a"), (2, "b c")]).with_priors(Priors::Uniform);
+
+        // Version 1 predates document counts, which are estimated from the token counts.
+        for (saved, documents) in [(version_1, 3.0), (version_2, 1.0)] {
+            let loaded: NaiveBayesClassifier<u32> = NaiveBayesClassifier::read_binary(saved)
+                .unwrap()
+                .with_priors(Priors::Uniform);
+
+            assert_eq!(loaded.class_document_count(&1), documents);
+            assert_close(
+                &loaded.log_scores(Words::new("a c d")),
+                &expected.log_scores(Words::new("a c d")),
+            );
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Saves a map keyed by class label as a list of `(label, value)` pairs, because JSON object keys
+/// can only be strings and class labels need not be.
+pub fn serialize<T, V, S>(map: &HashMap<T, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+/// Reads the pairs, or a map with label keys as saved by earlier format versions.
+pub fn deserialize<'de, T, V, D>(deserializer: D) -> Result<HashMap<T, V>, D::Error>
+where
+    T: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(ClassMapVisitor(PhantomData))
+}
+
+struct ClassMapVisitor<T, V>(PhantomData<(T, V)>);
+
+impl<'de, T, V> Visitor<'de> for ClassMapVisitor<T, V>
+where
+    T: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = HashMap<T, V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of (class, value) pairs or a map from class to value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<HashMap<T, V>, A::Error> {
+        let mut map = HashMap::new();
+
+        while let Some((class, value)) = seq.next_element()? {
+            map.insert(class, value);
+        }
+
+        Ok(map)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<HashMap<T, V>, A::Error> {
+        let mut map = HashMap::new();
+
+        while let Some((class, value)) = access.next_entry()? {
+            map.insert(class, value);
+        }
+
+        Ok(map)
+    }
+}
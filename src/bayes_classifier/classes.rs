@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Deref;
+
+#[cfg(feature = "serde")]
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Class labels in training order, each mapped to its dense column index so that labels are
+/// looked up by hash rather than by scanning. Dereferences to the labels.
+#[derive(Clone)]
+pub(crate) struct Classes<T: Hash + Eq + Clone> {
+    labels: Vec<T>,
+    columns: HashMap<T, usize>,
+}
+
+impl<T: Hash + Eq + Clone> Classes<T> {
+    pub fn new() -> Classes<T> {
+        Classes {
+            labels: Vec::new(),
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn column(&self, class: &T) -> Option<usize> {
+        self.columns.get(class).cloned()
+    }
+
+    /// Appends `class` in a new last column. Returns whether it was new.
+    pub fn push(&mut self, class: T) -> bool {
+        if self.columns.contains_key(&class) {
+            return false;
+        }
+
+        self.columns.insert(class.clone(), self.labels.len());
+        self.labels.push(class);
+        true
+    }
+
+    /// Removes the class at `column`, shifting the later classes down one column.
+    pub fn remove(&mut self, column: usize) {
+        let class = self.labels.remove(column);
+        self.columns.remove(&class);
+
+        for (later, class) in self.labels.iter().enumerate().skip(column) {
+            self.columns.insert(class.clone(), later);
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone> Deref for Classes<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.labels
+    }
+}
+
+// Saved as the plain list of labels, as before the index existed.
+#[cfg(feature = "serde")]
+impl<T: Hash + Eq + Clone + Serialize> Serialize for Classes<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.labels.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Hash + Eq + Clone + Deserialize<'de>> Deserialize<'de> for Classes<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Classes<T>, D::Error> {
+        let mut classes = Classes::new();
+
+        for class in Vec::deserialize(deserializer)? {
+            if !classes.push(class) {
+                return Err(D::Error::custom("duplicate class label"));
+            }
+        }
+
+        Ok(classes)
+    }
+}
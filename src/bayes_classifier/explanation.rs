@@ -0,0 +1,303 @@
+use super::{
+    normalize, rank, DataType, NaiveBayesClassifier, Number, Prediction, ProbabilityMap, Token,
+    TokenizeBorrowed,
+};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::Hash;
+
+/// How much one distinct input token added to the log score of each class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenContribution<T: Hash + Eq, K = DataType> {
+    pub datum: K,
+    /// Summed weight of every occurrence of the token in the input.
+    pub weight: Number,
+    /// Whether the token was seen in training. Unknown tokens only appear here when the unknown
+    /// token policy scores them.
+    pub known: bool,
+    pub log_likelihoods: ProbabilityMap<T>,
+}
+
+impl<T: Hash + Eq, K> TokenContribution<T, K> {
+    /// Spread between the classes the token favours most and least.
+    pub fn influence(&self) -> Number {
+        let (min, max) = self.log_likelihoods.values().fold(
+            (Number::INFINITY, Number::NEG_INFINITY),
+            |(min, max), value| (min.min(*value), max.max(*value)),
+        );
+
+        if min > max {
+            0.0
+        } else {
+            max - min
+        }
+    }
+
+    /// How far the token favours `class` over the best of the other classes. Negative when the
+    /// token speaks against `class`.
+    pub fn influence_on(&self, class: &T) -> Number {
+        let own = self.log_likelihoods.get(class).cloned().unwrap_or(0.0);
+        let best_other = self
+            .log_likelihoods
+            .iter()
+            .filter(|&(other, _)| other != class)
+            .map(|(_, value)| *value)
+            .fold(Number::NEG_INFINITY, Number::max);
+
+        if best_other == Number::NEG_INFINITY {
+            0.0
+        } else {
+            own - best_other
+        }
+    }
+}
+
+/// Breakdown of a prediction into the prior and per-token terms that make up each class's log
+/// score, as returned by `NaiveBayesClassifier::explain`.
+///
+/// For every class, `log_priors + baseline + Σ tokens.log_likelihoods` equals `log_scores`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation<T: Hash + Eq, K = DataType> {
+    /// Classes in training order.
+    pub classes: Vec<T>,
+    pub log_priors: ProbabilityMap<T>,
+    /// Terms that do not belong to any input token, such as the Bernoulli probability of every
+    /// vocabulary token absent from the input. Zero for the other variants.
+    pub baseline: ProbabilityMap<T>,
+    /// One entry per distinct scored token, in order of first appearance until sorted.
+    pub tokens: Vec<TokenContribution<T, K>>,
+    /// Distinct input tokens missing from the vocabulary, whether scored or skipped.
+    pub unknown: Vec<K>,
+    pub log_scores: ProbabilityMap<T>,
+    pub posteriors: ProbabilityMap<T>,
+}
+
+impl<T: Hash + Eq + Clone, K> Explanation<T, K> {
+    /// Classes by descending posterior.
+    pub fn ranking(&self) -> Vec<Prediction<T>> {
+        rank(&self.classes, &self.posteriors)
+    }
+
+    /// Orders `tokens` by descending `TokenContribution::influence`.
+    pub fn sort_by_influence(&mut self) {
+        self.tokens.sort_by(|a, b| {
+            b.influence()
+                .partial_cmp(&a.influence())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    /// Orders `tokens` from the strongest evidence for `class` to the strongest against it.
+    pub fn sort_by_influence_on(&mut self, class: &T) {
+        self.tokens.sort_by(|a, b| {
+            b.influence_on(class)
+                .partial_cmp(&a.influence_on(class))
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+}
+
+/// A table with one column per class, best first, and one row per term of the log score. When no
+/// class explains the input there are no posteriors, and the classes are in training order.
+impl<T: Hash + Eq + Clone + fmt::Display, K: fmt::Debug> fmt::Display for Explanation<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let classes: Vec<T> = if self.posteriors.is_empty() {
+            self.classes.clone()
+        } else {
+            self.ranking()
+                .into_iter()
+                .map(|prediction| prediction.class)
+                .collect()
+        };
+
+        let token_labels: Vec<String> = self
+            .tokens
+            .iter()
+            .map(|token| {
+                let mut label = format!("{:?}", token.datum);
+
+                if token.weight != 1.0 {
+                    label.push_str(&format!(" x{}", token.weight));
+                }
+                if !token.known {
+                    label.push_str(" (unknown)");
+                }
+
+                label
+            })
+            .collect();
+
+        let label_width = token_labels
+            .iter()
+            .map(|label| label.chars().count())
+            .chain(Some("log score".len()))
+            .max()
+            .unwrap_or(0);
+        let column_width = classes
+            .iter()
+            .map(|class| class.to_string().chars().count())
+            .chain(Some(10))
+            .max()
+            .unwrap_or(10);
+
+        let row = |f: &mut fmt::Formatter, label: &str, values: &ProbabilityMap<T>| {
+            write!(f, "{:<width$}", label, width = label_width)?;
+            for class in classes.iter() {
+                let value = values.get(class).cloned().unwrap_or(0.0);
+                write!(f, "  {:>width$.4}", value, width = column_width)?;
+            }
+            writeln!(f)
+        };
+
+        write!(f, "{:<width$}", "", width = label_width)?;
+        for class in classes.iter() {
+            write!(f, "  {:>width$}", class.to_string(), width = column_width)?;
+        }
+        writeln!(f)?;
+
+        if !self.posteriors.is_empty() {
+            row(f, "posterior", &self.posteriors)?;
+        }
+        row(f, "log prior", &self.log_priors)?;
+        if self.baseline.values().any(|value| *value != 0.0) {
+            row(f, "baseline", &self.baseline)?;
+        }
+        for (token, label) in self.tokens.iter().zip(token_labels.iter()) {
+            row(f, label, &token.log_likelihoods)?;
+        }
+        row(f, "log score", &self.log_scores)?;
+
+        if !self.unknown.is_empty() {
+            let unknown: Vec<String> = self
+                .unknown
+                .iter()
+                .map(|datum| format!("{:?}", datum))
+                .collect();
+
+            writeln!(f, "unknown: {}", unknown.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Hash + Eq + Clone, K: Token> NaiveBayesClassifier<T, K> {
+    /// Scores `data` like `analyze`, but keeps each token's contribution to each class so the
+    /// prediction can be inspected.
+    pub fn explain<U: TokenizeBorrowed<K>>(&self, data: U) -> Explanation<T, K> {
+        let breakdown = self.breakdown(data.weighted_tokens_borrowed());
+        let by_class = |values: &[Number]| -> ProbabilityMap<T> {
+            self.classes
+                .iter()
+                .cloned()
+                .zip(values.iter().cloned())
+                .collect()
+        };
+
+        let log_priors = by_class(&self.log_priors(self.tables(), &self.priors));
+
+        let baseline = by_class(&breakdown.baseline);
+        let tokens: Vec<TokenContribution<T, K>> = breakdown
+            .tokens
+            .into_iter()
+            .map(|terms| TokenContribution {
+                log_likelihoods: by_class(&terms.log_likelihoods),
+                datum: terms.datum.into_owned(),
+                weight: terms.weight,
+                known: terms.known,
+            })
+            .collect();
+
+        let log_scores: ProbabilityMap<T> = self
+            .classes
+            .iter()
+            .map(|class| {
+                let log_score = log_priors[class]
+                    + baseline[class]
+                    + tokens
+                        .iter()
+                        .map(|token| token.log_likelihoods[class])
+                        .sum::<Number>();
+
+                (class.clone(), log_score)
+            })
+            .collect();
+
+        Explanation {
+            classes: self.classes.to_vec(),
+            posteriors: normalize(&log_scores),
+            log_priors,
+            baseline,
+            tokens,
+            unknown: breakdown.unknown.into_iter().map(Cow::into_owned).collect(),
+            log_scores,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::trained;
+    use super::super::{NaiveBayesClassifier, Number, Smoothing, UnknownTokens, Variant, Words};
+
+    #[test]
+    fn terms_add_up_to_the_log_scores() {
+        let documents = [(1, "a b a"), (2, "b c"), (3, "c d d"), (1, "a e")];
+
+        for variant in [
+            Variant::Multinomial,
+            Variant::Bernoulli,
+            Variant::Complement,
+        ] {
+            for unknown_tokens in [
+                UnknownTokens::Ignore,
+                UnknownTokens::Smooth,
+                UnknownTokens::Token,
+            ] {
+                let classifier = trained(&documents)
+                    .with_variant(variant)
+                    .with_unknown_tokens(unknown_tokens);
+                let text = "a a c z y";
+                let explanation = classifier.explain(Words::new(text));
+                let log_scores = classifier.log_scores(Words::new(text));
+
+                for class in explanation.classes.iter() {
+                    let sum = explanation.log_priors[class]
+                        + explanation.baseline[class]
+                        + explanation
+                            .tokens
+                            .iter()
+                            .map(|token| token.log_likelihoods[class])
+                            .sum::<Number>();
+
+                    assert!(
+                        (sum - log_scores[class]).abs() < 1e-9,
+                        "{:?} {:?}: {} != {}",
+                        variant,
+                        unknown_tokens,
+                        sum,
+                        log_scores[class]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_report_lists_every_class_when_none_explains_the_input() {
+        let mut classifier = NaiveBayesClassifier::new().with_smoothing(Smoothing::None);
+        classifier.train(1, Words::new("a"));
+        classifier.train(2, Words::new("b"));
+        let explanation = classifier.explain(Words::new("a b"));
+
+        assert!(explanation.posteriors.is_empty());
+
+        let report = explanation.to_string();
+        let header = report.lines().next().unwrap();
+
+        assert_eq!(header.split_whitespace().collect::<Vec<_>>(), ["1", "2"]);
+        assert!(!report.contains("posterior"));
+        assert!(report.contains("-inf"));
+    }
+}
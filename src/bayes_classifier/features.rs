@@ -0,0 +1,189 @@
+use super::{DataType, NaiveBayesClassifier, Number, Token, Variant};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::Hash;
+
+/// A token that points strongly to one class, as listed by
+/// `NaiveBayesClassifier::most_informative_features`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InformativeFeature<T, K = DataType> {
+    pub datum: K,
+    /// How much more the token's presence adds to the log score of the class than to that of
+    /// `versus`, under the classifier's variant and smoothing.
+    pub log_ratio: Number,
+    /// The other class whose log score the token raises most.
+    pub versus: T,
+}
+
+/// What the classifier learned about one class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassSummary<T, K = DataType> {
+    pub class: T,
+    /// Number of distinct tokens seen in the class.
+    pub vocabulary_size: usize,
+    pub token_total: Number,
+    pub document_total: Number,
+    /// Most informative tokens first.
+    pub features: Vec<InformativeFeature<T, K>>,
+}
+
+impl<T: fmt::Display, K: fmt::Debug> fmt::Display for ClassSummary<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} documents, {} tokens, {} distinct",
+            self.class, self.document_total, self.token_total, self.vocabulary_size
+        )?;
+
+        let datum_width = self
+            .features
+            .iter()
+            .map(|feature| format!("{:?}", feature.datum).chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for feature in self.features.iter() {
+            writeln!(
+                f,
+                "  {:<width$}  {:>8.1} : 1 against {}",
+                format!("{:?}", feature.datum),
+                feature.log_ratio.exp(),
+                feature.versus,
+                width = datum_width
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Hash + Eq + Clone, K: Token> NaiveBayesClassifier<T, K> {
+    /// Summarises every class in training order, listing up to `n` of its tokens ranked by how
+    /// much more their presence raises the class's log score than that of any other class.
+    pub fn most_informative_features(&self, n: usize) -> Vec<ClassSummary<T, K>> {
+        let entries = self.datum_counts.entries();
+
+        self.classes
+            .iter()
+            .enumerate()
+            .map(|(column, class)| {
+                let mut features: Vec<InformativeFeature<T, K>> = entries
+                    .iter()
+                    .filter_map(|&(datum, id)| {
+                        self.log_ratio(id, column)
+                            .map(|(log_ratio, versus)| InformativeFeature {
+                                datum: datum.clone(),
+                                log_ratio,
+                                versus: self.classes[versus].clone(),
+                            })
+                    })
+                    .collect();
+
+                features.sort_by(|a, b| {
+                    b.log_ratio
+                        .partial_cmp(&a.log_ratio)
+                        .unwrap_or(Ordering::Equal)
+                });
+                features.truncate(n);
+
+                ClassSummary {
+                    class: class.clone(),
+                    vocabulary_size: entries
+                        .iter()
+                        .filter(|&&(_, id)| self.datum_counts.row(id)[column] > 0.0)
+                        .count(),
+                    token_total: self.class_counts.count(class),
+                    document_total: self.class_document_counts.count(class),
+                    features,
+                }
+            })
+            .collect()
+    }
+
+    /// How strongly the token points to any one class: its largest `log_ratio` over all classes,
+    /// or zero with fewer than two classes.
+    pub(crate) fn informativeness(&self, id: usize) -> Number {
+        (0..self.classes.len())
+            .filter_map(|column| self.log_ratio(id, column))
+            .map(|(log_ratio, _)| log_ratio)
+            .fold(0.0, Number::max)
+    }
+
+    /// How much more the token raises the log score of the class at `column` than that of the
+    /// class it raises most otherwise, and that other class's column. `None` with fewer than two
+    /// classes.
+    fn log_ratio(&self, id: usize, column: usize) -> Option<(Number, usize)> {
+        let log_weights = self.log_weights(id);
+
+        log_weights
+            .iter()
+            .enumerate()
+            .filter(|&(other, _)| other != column)
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map(|(other, log_weight)| (log_weights[column] - log_weight, other))
+    }
+
+    /// What one occurrence of the token adds to each class's log score, indexed by class column,
+    /// computed from the same `ScoringTables` as scoring. For the Bernoulli variant this is the
+    /// log probability of the token being present less that of it being absent, since an absent
+    /// token also counts.
+    fn log_weights(&self, id: usize) -> Vec<Number> {
+        let tables = self.tables();
+        let mut log_weights: Vec<Number> = vec![0.0; self.classes.len()];
+
+        self.add_token_terms(tables, Some(id), 1.0, &mut log_weights);
+
+        if self.variant == Variant::Bernoulli {
+            let alpha = self.smoothing.alpha();
+            let row = self.datum_counts.document_row(id);
+
+            for (column, (log_weight, denominator)) in log_weights
+                .iter_mut()
+                .zip(tables.denominators.iter())
+                .enumerate()
+            {
+                *log_weight -= (1.0 - (row[column] + alpha) / denominator).ln();
+            }
+        }
+
+        log_weights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::trained;
+    use super::super::Words;
+    use super::*;
+
+    #[test]
+    fn log_ratios_match_how_tokens_move_the_log_scores() {
+        let documents = [(1, "a b a"), (2, "b c"), (3, "c d d"), (1, "a e"), (2, "c")];
+
+        for variant in [
+            Variant::Multinomial,
+            Variant::Bernoulli,
+            Variant::Complement,
+        ] {
+            let classifier = trained(&documents).with_variant(variant);
+            let without = classifier.log_scores(Words::new(""));
+
+            for summary in classifier.most_informative_features(10) {
+                for feature in summary.features {
+                    let with = classifier.log_scores(Words::new(&feature.datum));
+                    let expected = (with[&summary.class] - without[&summary.class])
+                        - (with[&feature.versus] - without[&feature.versus]);
+
+                    assert!(
+                        (feature.log_ratio - expected).abs() < 1e-9,
+                        "{:?} {:?}: {} != {}",
+                        variant,
+                        feature.datum,
+                        feature.log_ratio,
+                        expected
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use super::{normalize, rank, Counts, Error, Number, Prediction, ProbabilityMap};
+use std::f64::consts::PI;
+use std::hash::Hash;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Mean and variance of one feature, updated one sample at a time with Welford's algorithm.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct RunningStatistics {
+    count: Number,
+    mean: Number,
+    sum_of_squared_deviations: Number,
+}
+
+impl RunningStatistics {
+    pub fn update(&mut self, value: Number) {
+        self.count += 1.0;
+
+        let deviation = value - self.mean;
+        self.mean += deviation / self.count;
+        self.sum_of_squared_deviations += deviation * (value - self.mean);
+    }
+
+    pub fn mean(&self) -> Number {
+        self.mean
+    }
+
+    pub fn variance(&self) -> Number {
+        if self.count > 0.0 {
+            self.sum_of_squared_deviations / self.count
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Naive Bayes over fixed-length vectors of continuous features, modelling each feature as
+/// normally distributed within each class.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: Hash + Eq + Clone + Serialize",
+        deserialize = "T: Hash + Eq + Clone + Deserialize<'de>"
+    ))
+)]
+pub struct GaussianNaiveBayesClassifier<T: Hash + Eq + Clone> {
+    classes: Vec<T>,
+    sample_counts: Counts<T>,
+    feature_statistics: Vec<Vec<RunningStatistics>>,
+    overall_statistics: Vec<RunningStatistics>,
+    variance_smoothing: Number,
+}
+
+impl<T: Hash + Eq + Clone> Default for GaussianNaiveBayesClassifier<T> {
+    fn default() -> GaussianNaiveBayesClassifier<T> {
+        GaussianNaiveBayesClassifier::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone> GaussianNaiveBayesClassifier<T> {
+    pub fn new() -> GaussianNaiveBayesClassifier<T> {
+        GaussianNaiveBayesClassifier {
+            classes: Vec::new(),
+            sample_counts: Counts::new(),
+            feature_statistics: Vec::new(),
+            overall_statistics: Vec::new(),
+            variance_smoothing: 1e-9,
+        }
+    }
+
+    /// Sets the fraction of the largest feature variance added to every variance, which keeps
+    /// features that are constant within a class from producing infinite likelihoods.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `variance_smoothing` is negative or not finite.
+    pub fn with_variance_smoothing(
+        mut self,
+        variance_smoothing: Number,
+    ) -> GaussianNaiveBayesClassifier<T> {
+        check_variance_smoothing(variance_smoothing);
+        self.variance_smoothing = variance_smoothing;
+        self
+    }
+
+    pub fn variance_smoothing(&self) -> Number {
+        self.variance_smoothing
+    }
+
+    pub fn classes(&self) -> &[T] {
+        &self.classes
+    }
+
+    /// Length of the feature vectors, fixed by the first training sample.
+    pub fn feature_count(&self) -> Option<usize> {
+        if self.classes.is_empty() {
+            None
+        } else {
+            Some(self.overall_statistics.len())
+        }
+    }
+
+    /// Fails without changing the model if `features` has the wrong length or a value that is
+    /// not finite.
+    pub fn train(&mut self, class: T, features: &[Number]) -> Result<(), Error> {
+        self.check_features(features)?;
+
+        if self.classes.is_empty() {
+            self.overall_statistics = vec![RunningStatistics::default(); features.len()];
+        }
+
+        let column = match self.classes.iter().position(|existing| *existing == class) {
+            Some(column) => column,
+            None => {
+                self.classes.push(class.clone());
+                self.feature_statistics
+                    .push(vec![RunningStatistics::default(); features.len()]);
+                self.classes.len() - 1
+            }
+        };
+
+        for (feature, value) in features.iter().enumerate() {
+            self.feature_statistics[column][feature].update(*value);
+            self.overall_statistics[feature].update(*value);
+        }
+
+        self.sample_counts.total += 1.0;
+        *self.sample_counts.per_class.entry(class).or_insert(0.0) += 1.0;
+
+        Ok(())
+    }
+
+    pub fn log_scores(&self, features: &[Number]) -> Result<ProbabilityMap<T>, Error> {
+        self.check_features(features)?;
+
+        let largest_variance = self
+            .overall_statistics
+            .iter()
+            .map(RunningStatistics::variance)
+            .fold(0.0, Number::max);
+        let epsilon = if largest_variance > 0.0 {
+            self.variance_smoothing * largest_variance
+        } else {
+            self.variance_smoothing
+        };
+
+        let mut log_scores: ProbabilityMap<T> = ProbabilityMap::new();
+
+        for (column, class) in self.classes.iter().enumerate() {
+            let probability_of_class = self.sample_counts.count(class) / self.sample_counts.total;
+            let mut log_score = probability_of_class.ln();
+
+            for (statistics, value) in self.feature_statistics[column].iter().zip(features) {
+                log_score += gaussian_log_density(
+                    *value,
+                    statistics.mean(),
+                    statistics.variance() + epsilon,
+                );
+            }
+
+            log_scores.insert(class.clone(), log_score);
+        }
+
+        Ok(log_scores)
+    }
+
+    pub fn analyze(&self, features: &[Number]) -> Result<ProbabilityMap<T>, Error> {
+        Ok(normalize(&self.log_scores(features)?))
+    }
+
+    pub fn predict(&self, features: &[Number]) -> Result<Option<Prediction<T>>, Error> {
+        Ok(rank(&self.classes, &self.analyze(features)?)
+            .into_iter()
+            .next())
+    }
+
+    fn check_features(&self, features: &[Number]) -> Result<(), Error> {
+        match self.feature_count() {
+            Some(expected) if expected != features.len() => {
+                return Err(Error::FeatureLength {
+                    expected,
+                    found: features.len(),
+                })
+            }
+            _ => {}
+        }
+
+        for (feature, value) in features.iter().enumerate() {
+            if !value.is_finite() {
+                return Err(Error::InvalidFeature {
+                    feature,
+                    value: *value,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Panics unless `variance_smoothing` is finite and non-negative.
+pub(crate) fn check_variance_smoothing(variance_smoothing: Number) {
+    if !(variance_smoothing.is_finite() && variance_smoothing >= 0.0) {
+        panic!(
+            "variance smoothing {} is not finite and non-negative",
+            variance_smoothing
+        );
+    }
+}
+
+pub(crate) fn gaussian_log_density(value: Number, mean: Number, variance: Number) -> Number {
+    let deviation = value - mean;
+
+    -0.5 * (2.0 * PI * variance).ln() - deviation * deviation / (2.0 * variance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_statistics_match_the_direct_formulas() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mut statistics = RunningStatistics::default();
+
+        for value in values.iter() {
+            statistics.update(*value);
+        }
+
+        assert!((statistics.mean() - 5.0).abs() < 1e-12);
+        assert!((statistics.variance() - 4.0).abs() < 1e-12);
+        assert_eq!(RunningStatistics::default().variance(), 0.0);
+    }
+
+    #[test]
+    fn log_scores_add_the_prior_and_each_feature_density() {
+        let mut classifier = GaussianNaiveBayesClassifier::new().with_variance_smoothing(0.0);
+        classifier.train(1, &[1.0, 10.0]).unwrap();
+        classifier.train(1, &[3.0, 14.0]).unwrap();
+        classifier.train(2, &[6.0, 0.0]).unwrap();
+        classifier.train(2, &[8.0, 2.0]).unwrap();
+        classifier.train(2, &[7.0, 1.0]).unwrap();
+
+        let log_scores = classifier.log_scores(&[2.0, 11.0]).unwrap();
+        let expected_1 = (2.0 as Number / 5.0).ln()
+            + gaussian_log_density(2.0, 2.0, 1.0)
+            + gaussian_log_density(11.0, 12.0, 4.0);
+        let expected_2 = (3.0 as Number / 5.0).ln()
+            + gaussian_log_density(2.0, 7.0, 2.0 / 3.0)
+            + gaussian_log_density(11.0, 1.0, 2.0 / 3.0);
+
+        assert!((log_scores[&1] - expected_1).abs() < 1e-9);
+        assert!((log_scores[&2] - expected_2).abs() < 1e-9);
+        assert_eq!(classifier.predict(&[2.0, 11.0]).unwrap().unwrap().class, 1);
+        assert!((gaussian_log_density(0.0, 0.0, 1.0) + 0.5 * (2.0 * PI).ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn features_that_are_not_finite_are_rejected() {
+        let mut classifier = GaussianNaiveBayesClassifier::new();
+        classifier.train(1, &[1.0, 2.0]).unwrap();
+
+        for value in [Number::NAN, Number::INFINITY, Number::NEG_INFINITY] {
+            assert!(matches!(
+                classifier.train(1, &[value, 2.0]),
+                Err(Error::InvalidFeature { feature: 0, .. })
+            ));
+            assert!(matches!(
+                classifier.log_scores(&[1.0, value]),
+                Err(Error::InvalidFeature { feature: 1, .. })
+            ));
+        }
+
+        assert!(classifier.log_scores(&[1.0, 2.0]).unwrap()[&1].is_finite());
+    }
+
+    #[test]
+    #[should_panic(expected = "not finite and non-negative")]
+    fn variance_smoothing_must_be_non_negative() {
+        let _: GaussianNaiveBayesClassifier<u32> =
+            GaussianNaiveBayesClassifier::new().with_variance_smoothing(-1.0);
+    }
+}
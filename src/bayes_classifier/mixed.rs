@@ -0,0 +1,361 @@
+use super::gaussian::{check_variance_smoothing, gaussian_log_density, RunningStatistics};
+use super::{
+    log_sum_exp, normalize, rank, Counts, DataType, Error, Number, Prediction, ProbabilityMap,
+    Smoothing,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How one column of a `MixedNaiveBayesClassifier` record is modelled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Column {
+    /// Discrete values, counted per class.
+    Categorical,
+    /// Numeric values, modelled as normally distributed within each class.
+    Gaussian,
+    /// Numeric values, modelled with a Gaussian kernel density estimate over the training values
+    /// of each class.
+    Kernel { bandwidth: Number },
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Column::Categorical => f.write_str("categorical"),
+            Column::Gaussian | Column::Kernel { .. } => f.write_str("numeric"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Categorical(DataType),
+    Numeric(Number),
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: Hash + Eq + Clone + Serialize",
+        deserialize = "T: Hash + Eq + Clone + Deserialize<'de>"
+    ))
+)]
+enum ColumnModel<T: Hash + Eq + Clone> {
+    Categorical {
+        value_counts: HashMap<DataType, Counts<T>>,
+    },
+    Gaussian {
+        #[cfg_attr(feature = "serde", serde(with = "super::class_map"))]
+        statistics: HashMap<T, RunningStatistics>,
+        overall_statistics: RunningStatistics,
+    },
+    Kernel {
+        bandwidth: Number,
+        #[cfg_attr(feature = "serde", serde(with = "super::class_map"))]
+        samples: HashMap<T, Vec<Number>>,
+    },
+}
+
+/// Naive Bayes over records with a fixed schema of categorical and numeric columns, combining
+/// the per-column likelihoods in log space.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: Hash + Eq + Clone + Serialize",
+        deserialize = "T: Hash + Eq + Clone + Deserialize<'de>"
+    ))
+)]
+pub struct MixedNaiveBayesClassifier<T: Hash + Eq + Clone> {
+    schema: Vec<Column>,
+    classes: Vec<T>,
+    sample_counts: Counts<T>,
+    column_models: Vec<ColumnModel<T>>,
+    smoothing: Smoothing,
+    variance_smoothing: Number,
+}
+
+impl<T: Hash + Eq + Clone> MixedNaiveBayesClassifier<T> {
+    /// Fails if a `Column::Kernel` bandwidth is not finite and positive.
+    pub fn new(schema: Vec<Column>) -> Result<MixedNaiveBayesClassifier<T>, Error> {
+        for (index, column) in schema.iter().enumerate() {
+            if let Column::Kernel { bandwidth } = *column {
+                if !(bandwidth.is_finite() && bandwidth > 0.0) {
+                    return Err(Error::InvalidBandwidth {
+                        column: index,
+                        bandwidth,
+                    });
+                }
+            }
+        }
+
+        let column_models = schema
+            .iter()
+            .map(|column| match *column {
+                Column::Categorical => ColumnModel::Categorical {
+                    value_counts: HashMap::new(),
+                },
+                Column::Gaussian => ColumnModel::Gaussian {
+                    statistics: HashMap::new(),
+                    overall_statistics: RunningStatistics::default(),
+                },
+                Column::Kernel { bandwidth } => ColumnModel::Kernel {
+                    bandwidth,
+                    samples: HashMap::new(),
+                },
+            })
+            .collect();
+
+        Ok(MixedNaiveBayesClassifier {
+            schema,
+            classes: Vec::new(),
+            sample_counts: Counts::new(),
+            column_models,
+            smoothing: Smoothing::default(),
+            variance_smoothing: 1e-9,
+        })
+    }
+
+    /// Sets the additive smoothing applied to categorical columns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pseudocount of `smoothing` is negative or not finite.
+    pub fn with_smoothing(mut self, smoothing: Smoothing) -> MixedNaiveBayesClassifier<T> {
+        if let Err(reason) = smoothing.check() {
+            panic!("{}", reason);
+        }
+
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Sets the fraction of each Gaussian column's overall variance added to its per-class
+    /// variances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `variance_smoothing` is negative or not finite.
+    pub fn with_variance_smoothing(
+        mut self,
+        variance_smoothing: Number,
+    ) -> MixedNaiveBayesClassifier<T> {
+        check_variance_smoothing(variance_smoothing);
+        self.variance_smoothing = variance_smoothing;
+        self
+    }
+
+    pub fn schema(&self) -> &[Column] {
+        &self.schema
+    }
+
+    pub fn classes(&self) -> &[T] {
+        &self.classes
+    }
+
+    pub fn train(&mut self, class: T, record: &[Value]) -> Result<(), Error> {
+        self.check_record(record)?;
+
+        for (column_model, value) in self.column_models.iter_mut().zip(record) {
+            match (column_model, value) {
+                (ColumnModel::Categorical { value_counts }, Value::Categorical(category)) => {
+                    let counts = value_counts.entry(category.clone()).or_default();
+
+                    counts.total += 1.0;
+                    *counts.per_class.entry(class.clone()).or_insert(0.0) += 1.0;
+                }
+                (
+                    ColumnModel::Gaussian {
+                        statistics,
+                        overall_statistics,
+                    },
+                    Value::Numeric(number),
+                ) => {
+                    statistics.entry(class.clone()).or_default().update(*number);
+                    overall_statistics.update(*number);
+                }
+                (ColumnModel::Kernel { samples, .. }, Value::Numeric(number)) => {
+                    samples.entry(class.clone()).or_default().push(*number);
+                }
+                _ => unreachable!("records are checked against the schema"),
+            }
+        }
+
+        if !self.classes.contains(&class) {
+            self.classes.push(class.clone());
+        }
+
+        self.sample_counts.total += 1.0;
+        *self.sample_counts.per_class.entry(class).or_insert(0.0) += 1.0;
+
+        Ok(())
+    }
+
+    pub fn log_scores(&self, record: &[Value]) -> Result<ProbabilityMap<T>, Error> {
+        self.check_record(record)?;
+
+        let mut log_scores: ProbabilityMap<T> = ProbabilityMap::new();
+
+        for class in self.classes.iter() {
+            let class_count = self.sample_counts.count(class);
+            let mut log_score = (class_count / self.sample_counts.total).ln();
+
+            for (column_model, value) in self.column_models.iter().zip(record) {
+                log_score += match (column_model, value) {
+                    (ColumnModel::Categorical { value_counts }, Value::Categorical(category)) => {
+                        let alpha = self.smoothing.alpha();
+                        let category_count = value_counts
+                            .get(category)
+                            .map_or(0.0, |counts| counts.count(class));
+                        // One extra slot for categories never seen in training.
+                        let category_total = value_counts.len() as Number + 1.0;
+
+                        ((category_count + alpha) / (class_count + alpha * category_total)).ln()
+                    }
+                    (
+                        ColumnModel::Gaussian {
+                            statistics,
+                            overall_statistics,
+                        },
+                        Value::Numeric(number),
+                    ) => {
+                        let epsilon = if overall_statistics.variance() > 0.0 {
+                            self.variance_smoothing * overall_statistics.variance()
+                        } else {
+                            self.variance_smoothing
+                        };
+                        let statistics = statistics.get(class).cloned().unwrap_or_default();
+
+                        gaussian_log_density(
+                            *number,
+                            statistics.mean(),
+                            statistics.variance() + epsilon,
+                        )
+                    }
+                    (ColumnModel::Kernel { bandwidth, samples }, Value::Numeric(number)) => {
+                        let samples = samples.get(class).map_or(&[][..], Vec::as_slice);
+                        let log_densities: Vec<Number> = samples
+                            .iter()
+                            .map(|sample| {
+                                gaussian_log_density(*number, *sample, bandwidth * bandwidth)
+                            })
+                            .collect();
+
+                        log_sum_exp(log_densities.iter()) - (samples.len() as Number).ln()
+                    }
+                    _ => unreachable!("records are checked against the schema"),
+                };
+            }
+
+            log_scores.insert(class.clone(), log_score);
+        }
+
+        Ok(log_scores)
+    }
+
+    pub fn analyze(&self, record: &[Value]) -> Result<ProbabilityMap<T>, Error> {
+        Ok(normalize(&self.log_scores(record)?))
+    }
+
+    pub fn predict(&self, record: &[Value]) -> Result<Option<Prediction<T>>, Error> {
+        Ok(rank(&self.classes, &self.analyze(record)?)
+            .into_iter()
+            .next())
+    }
+
+    fn check_record(&self, record: &[Value]) -> Result<(), Error> {
+        if record.len() != self.schema.len() {
+            return Err(Error::FeatureLength {
+                expected: self.schema.len(),
+                found: record.len(),
+            });
+        }
+
+        for (index, (column, value)) in self.schema.iter().zip(record).enumerate() {
+            let matches = matches!(
+                (column, value),
+                (Column::Categorical, Value::Categorical(_))
+                    | (Column::Gaussian, Value::Numeric(_))
+                    | (Column::Kernel { .. }, Value::Numeric(_))
+            );
+
+            if !matches {
+                return Err(Error::ColumnType {
+                    column: index,
+                    expected: column.to_string(),
+                });
+            }
+
+            if let Value::Numeric(number) = *value {
+                if !number.is_finite() {
+                    return Err(Error::InvalidFeature {
+                        feature: index,
+                        value: number,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_columns_need_a_positive_finite_bandwidth() {
+        for bandwidth in [0.0, -1.0, Number::NAN, Number::INFINITY] {
+            let schema = vec![Column::Categorical, Column::Kernel { bandwidth }];
+            let classifier: Result<MixedNaiveBayesClassifier<u32>, Error> =
+                MixedNaiveBayesClassifier::new(schema);
+
+            assert!(matches!(
+                classifier,
+                Err(Error::InvalidBandwidth { column: 1, .. })
+            ));
+        }
+
+        let schema = vec![Column::Kernel { bandwidth: 0.5 }];
+        let mut classifier: MixedNaiveBayesClassifier<u32> =
+            MixedNaiveBayesClassifier::new(schema).unwrap();
+        classifier.train(1, &[Value::Numeric(1.0)]).unwrap();
+
+        assert!(classifier.log_scores(&[Value::Numeric(1.2)]).unwrap()[&1].is_finite());
+    }
+
+    #[test]
+    fn numeric_values_that_are_not_finite_are_rejected() {
+        let schema = vec![Column::Categorical, Column::Gaussian];
+        let mut classifier: MixedNaiveBayesClassifier<u32> =
+            MixedNaiveBayesClassifier::new(schema).unwrap();
+        let record = |number| [Value::Categorical("a".to_string()), Value::Numeric(number)];
+        classifier.train(1, &record(1.0)).unwrap();
+
+        assert!(matches!(
+            classifier.train(1, &record(Number::NAN)),
+            Err(Error::InvalidFeature { feature: 1, .. })
+        ));
+        assert!(matches!(
+            classifier.log_scores(&record(Number::INFINITY)),
+            Err(Error::InvalidFeature { feature: 1, .. })
+        ));
+        assert!(classifier.log_scores(&record(2.0)).unwrap()[&1].is_finite());
+    }
+
+    #[test]
+    #[should_panic(expected = "not finite and non-negative")]
+    fn variance_smoothing_must_be_finite() {
+        let schema = vec![Column::Gaussian];
+        let _: MixedNaiveBayesClassifier<u32> = MixedNaiveBayesClassifier::new(schema)
+            .unwrap()
+            .with_variance_smoothing(Number::NAN);
+    }
+}
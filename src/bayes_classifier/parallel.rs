@@ -0,0 +1,76 @@
+use super::{NaiveBayesClassifier, Token, TokenizeBorrowed};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use std::hash::Hash;
+
+impl<T, K> NaiveBayesClassifier<T, K>
+where
+    T: Hash + Eq + Clone + Send + Sync,
+    K: Token + Send + Sync,
+{
+    /// Trains on `batch` across the rayon thread pool. Each thread counts into its own partial
+    /// model, and the partials are merged in batch order, so the result matches `train_batch`.
+    pub fn par_train_batch<I, U>(&mut self, batch: I)
+    where
+        I: IntoParallelIterator<Item = (T, U)>,
+        I::Iter: IndexedParallelIterator,
+        U: TokenizeBorrowed<K> + Send,
+    {
+        let partial = batch
+            .into_par_iter()
+            .fold(
+                || self.untrained(),
+                |mut partial, (class, data)| {
+                    partial.train(class, data);
+                    partial
+                },
+            )
+            .reduce(
+                || self.untrained(),
+                |mut left, right| {
+                    left.merge(&right)
+                        .expect("partial models share their smoothing");
+                    left
+                },
+            );
+
+        self.merge(&partial)
+            .expect("partial models share their smoothing");
+    }
+
+    /// An untrained classifier with the same configuration.
+    fn untrained(&self) -> NaiveBayesClassifier<T, K> {
+        NaiveBayesClassifier::new()
+            .with_smoothing(self.smoothing)
+            .with_unknown_tokens(self.unknown_tokens)
+            .with_reject_option(self.reject_option)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::assert_close;
+    use super::super::Words;
+    use super::*;
+
+    #[test]
+    fn training_in_parallel_matches_training_in_order() {
+        let texts = ["a b a", "b c", "c d e", "a e", "f", "b b d"];
+        let batch: Vec<(u32, Words)> = (0..600)
+            .map(|index| (index as u32 % 4, Words::new(texts[index % texts.len()])))
+            .collect();
+
+        let mut sequential: NaiveBayesClassifier<u32> = NaiveBayesClassifier::new();
+        sequential.train_batch(batch.iter().cloned());
+        let mut parallel: NaiveBayesClassifier<u32> = NaiveBayesClassifier::new();
+        parallel.par_train_batch(batch);
+
+        assert_eq!(parallel.classes(), sequential.classes());
+
+        for text in ["a", "b d g", "e e f"] {
+            assert_close(
+                &parallel.log_scores(Words::new(text)),
+                &sequential.log_scores(Words::new(text)),
+            );
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use super::{NaiveBayesClassifier, Number, Token, UnknownTokens, EPSILON};
+use std::cmp::Ordering;
+use std::hash::Hash;
+
+// Pruned tokens take their counts with them, so class token totals shrink to match. Under
+// `UnknownTokens::Token` their counts are folded into `Token::unknown` instead, which is never
+// pruned itself, so that pruned tokens go on scoring like any other unknown token.
+impl<T: Hash + Eq + Clone, K: Token> NaiveBayesClassifier<T, K> {
+    /// Drops tokens trained fewer than `min_count` times across all classes. Returns the number
+    /// of tokens dropped.
+    pub fn prune_min_count(&mut self, min_count: Number) -> usize {
+        let pruned = self
+            .prunable()
+            .into_iter()
+            .filter(|&(_, id)| self.datum_counts.total(id) < min_count)
+            .map(|(datum, _)| datum)
+            .collect();
+
+        self.prune(pruned)
+    }
+
+    /// Keeps only the `k` tokens trained most often across all classes. Returns the number of
+    /// tokens dropped.
+    ///
+    /// Under `UnknownTokens::Token` the unknown token that pruned tokens fold into is one of the
+    /// `k`, so it alone remains when `k` is zero.
+    pub fn prune_to_most_frequent(&mut self, k: usize) -> usize {
+        let pruned = self.prune_ranked(k, |classifier, id| classifier.datum_counts.total(id));
+        self.prune(pruned)
+    }
+
+    /// Keeps only the `k` tokens that point most strongly to a single class, as ranked by
+    /// `most_informative_features`. Returns the number of tokens dropped.
+    ///
+    /// As with `prune_to_most_frequent`, the unknown token is one of the `k`.
+    pub fn prune_to_most_informative(&mut self, k: usize) -> usize {
+        let pruned = self.prune_ranked(k, NaiveBayesClassifier::informativeness);
+        self.prune(pruned)
+    }
+
+    /// Drops tokens found in more than `max_fraction` of all training documents, such as stop
+    /// words. Returns the number of tokens dropped.
+    pub fn prune_max_document_frequency(&mut self, max_fraction: Number) -> usize {
+        let document_total = self.class_document_counts.total;
+        let pruned = self
+            .prunable()
+            .into_iter()
+            .filter(|&(_, id)| {
+                let documents: Number = self.datum_counts.document_row(id).iter().sum();
+
+                documents > max_fraction * document_total
+            })
+            .map(|(datum, _)| datum)
+            .collect();
+
+        self.prune(pruned)
+    }
+
+    /// Tokens eligible for pruning and their row ids, in row order.
+    fn prunable(&self) -> Vec<(K, usize)> {
+        let kept = self
+            .unknown_token_id()
+            .filter(|_| self.folds_into_unknown_token());
+
+        self.datum_counts
+            .entries()
+            .into_iter()
+            .filter(|&(_, id)| Some(id) != kept)
+            .map(|(datum, id)| (datum.clone(), id))
+            .collect()
+    }
+
+    /// The prunable tokens to drop so that `k` tokens remain, keeping those ranked highest by
+    /// `rank_by` and row order among ties. The unknown token pruned tokens fold into takes one
+    /// of the `k` once it exists.
+    fn prune_ranked<F>(&self, k: usize, rank_by: F) -> Vec<K>
+    where
+        F: Fn(&NaiveBayesClassifier<T, K>, usize) -> Number,
+    {
+        let mut ranked: Vec<(K, Number)> = self
+            .prunable()
+            .into_iter()
+            .map(|(datum, id)| (datum, rank_by(self, id)))
+            .collect();
+
+        let keeps_unknown = self.folds_into_unknown_token()
+            && (self.unknown_token_id().is_some() || ranked.len() > k);
+        let kept = if keeps_unknown {
+            k.saturating_sub(1)
+        } else {
+            k
+        };
+
+        ranked.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+        ranked
+            .into_iter()
+            .skip(kept)
+            .map(|(datum, _)| datum)
+            .collect()
+    }
+
+    fn folds_into_unknown_token(&self) -> bool {
+        self.unknown_tokens == UnknownTokens::Token && K::unknown().is_some()
+    }
+
+    fn prune(&mut self, pruned: Vec<K>) -> usize {
+        self.tables.take();
+
+        let unknown_token = K::unknown().filter(|_| self.folds_into_unknown_token());
+
+        for datum in pruned.iter() {
+            let id = match self.datum_counts.id(datum) {
+                Some(id) => id,
+                None => continue,
+            };
+            let counts = self.datum_counts.row(id).to_vec();
+            let documents = self.datum_counts.document_row(id).to_vec();
+
+            self.datum_counts.remove(datum);
+
+            if let Some(unknown_token) = unknown_token.clone() {
+                let unknown = self.datum_counts.intern(unknown_token);
+
+                for (column, class) in self.classes.iter().enumerate() {
+                    // A document holding several pruned tokens still holds the unknown token once,
+                    // so its document count cannot exceed the class's.
+                    let unknown_documents = (self.datum_counts.document_row(unknown)[column]
+                        + documents[column])
+                        .min(self.class_document_counts.count(class));
+
+                    self.datum_counts.add(unknown, column, counts[column]);
+                    self.datum_counts
+                        .set_documents(unknown, column, unknown_documents);
+                }
+            } else {
+                for (column, class) in self.classes.iter().enumerate() {
+                    if counts[column] > 0.0 {
+                        self.class_counts.remove(class, counts[column]);
+                    }
+                }
+            }
+        }
+
+        for column in (0..self.classes.len()).rev() {
+            let class = &self.classes[column];
+
+            if self.class_counts.count(class) <= EPSILON {
+                let document_count = self.class_document_counts.count(class);
+
+                self.class_document_counts.remove(class, document_count);
+                self.classes.remove(column);
+                self.datum_counts.remove_column(column);
+            }
+        }
+
+        pruned.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{assert_close, trained};
+    use super::super::Words;
+    use super::*;
+
+    /// Checks that the class totals are the sums of what the vocabulary holds.
+    fn assert_consistent(classifier: &NaiveBayesClassifier<u32>) {
+        let class_counts = &classifier.class_counts;
+
+        assert!(
+            (class_counts.total - class_counts.per_class.values().sum::<Number>()).abs() < 1e-9
+        );
+
+        for (column, class) in classifier.classes.iter().enumerate() {
+            let tokens: Number = classifier
+                .datum_counts
+                .ids()
+                .map(|id| classifier.datum_counts.row(id)[column])
+                .sum();
+            let documents = classifier.class_document_counts.count(class);
+
+            assert!((tokens - class_counts.count(class)).abs() < 1e-9);
+            assert!(classifier
+                .datum_counts
+                .ids()
+                .all(|id| classifier.datum_counts.document_row(id)[column] <= documents));
+        }
+    }
+
+    #[test]
+    fn pruning_rare_tokens_matches_never_training_them() {
+        let mut classifier = trained(&[(1, "a a b"), (2, "a c"), (3, "d")]);
+
+        assert_eq!(classifier.prune_min_count(2.0), 3);
+        assert_eq!(classifier.vocabulary_size(), 1);
+        assert_eq!(classifier.classes(), &[1, 2]);
+        assert_consistent(&classifier);
+        assert_close(
+            &classifier.log_scores(Words::new("a b")),
+            &trained(&[(1, "a a"), (2, "a")]).log_scores(Words::new("a b")),
+        );
+    }
+
+    #[test]
+    fn pruning_to_the_most_frequent_keeps_k_tokens() {
+        let mut classifier = trained(&[(1, "a a a b b c"), (2, "a d")]);
+
+        assert_eq!(classifier.prune_to_most_frequent(2), 2);
+        assert_eq!(classifier.vocabulary_size(), 2);
+        assert_consistent(&classifier);
+        assert_close(
+            &classifier.log_scores(Words::new("a b c")),
+            &trained(&[(1, "a a a b b"), (2, "a")]).log_scores(Words::new("a b c")),
+        );
+    }
+
+    #[test]
+    fn pruning_to_the_most_informative_drops_shared_tokens() {
+        let mut classifier = trained(&[(1, "a x"), (2, "b x"), (1, "a x")]);
+
+        assert_eq!(classifier.prune_to_most_informative(2), 1);
+        assert!(classifier.datum_counts.contains("a"));
+        assert!(classifier.datum_counts.contains("b"));
+        assert!(!classifier.datum_counts.contains("x"));
+        assert_consistent(&classifier);
+    }
+
+    #[test]
+    fn pruning_common_tokens_drops_stop_words() {
+        let mut classifier = trained(&[(1, "the a"), (2, "the b"), (1, "the c a")]);
+
+        assert_eq!(classifier.prune_max_document_frequency(0.7), 1);
+        assert!(!classifier.datum_counts.contains("the"));
+        assert!(classifier.datum_counts.contains("a"));
+        assert_consistent(&classifier);
+    }
+
+    #[test]
+    fn pruned_tokens_fold_into_the_unknown_token() {
+        let mut classifier = trained(&[(1, "a b"), (1, "c a"), (2, "d d e")])
+            .with_unknown_tokens(UnknownTokens::Token);
+
+        // "a" and the unknown token are the two kept.
+        assert_eq!(classifier.prune_to_most_frequent(2), 4);
+        assert_eq!(classifier.vocabulary_size(), 2);
+        assert_eq!(classifier.class_document_count(&1), 2.0);
+        assert_consistent(&classifier);
+
+        let unknown = classifier.datum_counts.id("<unk>").unwrap();
+
+        // Two pruned tokens in the one document of class 2 are in it once.
+        assert_eq!(classifier.datum_counts.row(unknown), &[2.0, 3.0]);
+        assert_eq!(classifier.datum_counts.document_row(unknown), &[2.0, 1.0]);
+        assert_close(
+            &classifier.log_scores(Words::new("b z")),
+            &classifier.log_scores(Words::new("c y")),
+        );
+    }
+}
@@ -0,0 +1,498 @@
+use super::{DataType, TokenizeBorrowed};
+use std::borrow::Cow;
+use std::char::ToLowercase;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What `WordTokenizer` does with apostrophes inside words, such as in "don't" or "d’euros".
+/// Typographic apostrophes are treated like ASCII ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Apostrophes {
+    /// Keep the word whole, as "don't" and "d'euros".
+    #[default]
+    Keep,
+    /// Split the word at its apostrophes, as "don" and "t", or "d" and "euros". Suited to
+    /// languages with elision, such as French and Italian.
+    Split,
+    /// Join the word without its apostrophes, as "dont" and "deuros".
+    Remove,
+}
+
+/// What `WordTokenizer` does with words joined by hyphens, such as "critical-care".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Hyphens {
+    /// Split the compound into its words, as "critical" and "care".
+    #[default]
+    Split,
+    /// Keep the compound whole, as "critical-care".
+    Keep,
+    /// Join the compound without its hyphens, as "criticalcare".
+    Remove,
+}
+
+/// Splits text into case-folded words at Unicode word boundaries, as defined by UAX #29.
+/// Punctuation, whitespace and symbols are dropped; numbers are kept as words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WordTokenizer {
+    apostrophes: Apostrophes,
+    hyphens: Hyphens,
+}
+
+impl WordTokenizer {
+    pub fn new() -> WordTokenizer {
+        WordTokenizer {
+            apostrophes: Apostrophes::default(),
+            hyphens: Hyphens::default(),
+        }
+    }
+
+    pub fn with_apostrophes(mut self, apostrophes: Apostrophes) -> WordTokenizer {
+        self.apostrophes = apostrophes;
+        self
+    }
+
+    pub fn with_hyphens(mut self, hyphens: Hyphens) -> WordTokenizer {
+        self.hyphens = hyphens;
+        self
+    }
+
+    pub fn apostrophes(&self) -> Apostrophes {
+        self.apostrophes
+    }
+
+    pub fn hyphens(&self) -> Hyphens {
+        self.hyphens
+    }
+
+    /// `text` paired with this tokenizer, to train on or score.
+    pub fn words<'a>(&self, text: &'a str) -> Words<'a> {
+        Words {
+            text,
+            tokenizer: *self,
+        }
+    }
+
+    /// The words of `text`, borrowed from it where they need no folding or rewriting, for use in
+    /// a `TokenizeBorrowed` implementation.
+    pub fn tokens<'a>(&self, text: &'a str) -> impl Iterator<Item = Cow<'a, str>> + 'a {
+        let tokenizer = *self;
+
+        self.spans(text)
+            .into_iter()
+            .map(move |(start, end)| tokenizer.finish(&text[start..end]))
+    }
+
+    /// Byte ranges of the words of `text`, after splitting at apostrophes and joining hyphenated
+    /// compounds as configured.
+    fn spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        // End of a hyphen directly following the last word, which the next word may join.
+        let mut hyphen_end: Option<usize> = None;
+
+        for (start, segment) in text.split_word_bound_indices() {
+            let end = start + segment.len();
+
+            if !segment.chars().any(char::is_alphanumeric) {
+                let follows_word = spans.last().is_some_and(|&(_, last)| last == start);
+
+                // Word boundaries fall on both sides of every hyphen, so it is a segment of its own.
+                hyphen_end = if self.hyphens != Hyphens::Split
+                    && follows_word
+                    && segment.chars().all(is_hyphen)
+                {
+                    Some(end)
+                } else {
+                    None
+                };
+                continue;
+            }
+
+            let mut pieces: Vec<(usize, usize)> = Vec::new();
+            if self.apostrophes == Apostrophes::Split {
+                let mut piece_start = start;
+
+                for (index, character) in segment.char_indices() {
+                    if is_apostrophe(character) {
+                        pieces.push((piece_start, start + index));
+                        piece_start = start + index + character.len_utf8();
+                    }
+                }
+                pieces.push((piece_start, end));
+            } else {
+                pieces.push((start, end));
+            }
+
+            for (piece_start, piece_end) in pieces {
+                if piece_start == piece_end {
+                    continue;
+                }
+
+                match spans.last_mut() {
+                    Some(last) if hyphen_end == Some(piece_start) => last.1 = piece_end,
+                    _ => spans.push((piece_start, piece_end)),
+                }
+                hyphen_end = None;
+            }
+        }
+
+        spans
+    }
+
+    /// Rewrites the apostrophes and hyphens left in `word`, then folds its case.
+    fn finish<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        if !word
+            .chars()
+            .any(|character| self.rewrite(character) != Some(character))
+        {
+            return fold_case(word);
+        }
+
+        let rewritten: String = word
+            .chars()
+            .filter_map(|character| self.rewrite(character))
+            .collect();
+
+        Cow::Owned(fold_case(&rewritten).into_owned())
+    }
+
+    fn rewrite(&self, character: char) -> Option<char> {
+        if is_apostrophe(character) {
+            match self.apostrophes {
+                Apostrophes::Keep => Some('\''),
+                Apostrophes::Split => Some(character),
+                Apostrophes::Remove => None,
+            }
+        } else if is_hyphen(character) {
+            match self.hyphens {
+                Hyphens::Keep => Some('-'),
+                Hyphens::Split => Some(character),
+                Hyphens::Remove => None,
+            }
+        } else {
+            Some(character)
+        }
+    }
+}
+
+/// Text split into words by a `WordTokenizer` when trained on or scored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Words<'a> {
+    text: &'a str,
+    tokenizer: WordTokenizer,
+}
+
+impl<'a> Words<'a> {
+    /// `text` split by the default `WordTokenizer`.
+    pub fn new(text: &'a str) -> Words<'a> {
+        WordTokenizer::new().words(text)
+    }
+}
+
+impl<'a> TokenizeBorrowed<DataType> for Words<'a> {
+    type Borrowed = str;
+
+    fn tokenize_borrowed<'b>(&'b self) -> impl Iterator<Item = Cow<'b, str>>
+    where
+        str: 'b,
+    {
+        self.tokenizer.tokens(self.text)
+    }
+}
+
+fn is_apostrophe(character: char) -> bool {
+    matches!(character, '\'' | '\u{2019}' | '\u{FF07}')
+}
+
+fn is_hyphen(character: char) -> bool {
+    matches!(character, '-' | '\u{2010}' | '\u{2011}')
+}
+
+/// Full Unicode case folding, the default mapping of `CaseFolding.txt`, so that words differing
+/// only in case compare equal: "Straße", "STRASSE" and "strasse" all fold to "strasse". Borrows
+/// `text` when it is already folded.
+pub fn fold_case(text: &str) -> Cow<'_, str> {
+    let first_change = text
+        .char_indices()
+        .find(|&(_, character)| !matches!(fold_char(character), Folding::Unchanged));
+
+    let index = match first_change {
+        Some((index, _)) => index,
+        None => return Cow::Borrowed(text),
+    };
+
+    let mut folded = String::with_capacity(text.len());
+    folded.push_str(&text[..index]);
+
+    for character in text[index..].chars() {
+        match fold_char(character) {
+            Folding::Unchanged => folded.push(character),
+            Folding::Char(character) => folded.push(character),
+            Folding::Str(string) => folded.push_str(string),
+            Folding::Lowercase(lowercase) => folded.extend(lowercase),
+        }
+    }
+
+    Cow::Owned(folded)
+}
+
+enum Folding {
+    Unchanged,
+    Char(char),
+    Str(&'static str),
+    Lowercase(ToLowercase),
+}
+
+fn fold_char(character: char) -> Folding {
+    if let Ok(index) = FOLDINGS.binary_search_by_key(&character, |&(from, _)| from) {
+        return Folding::Str(FOLDINGS[index].1);
+    }
+
+    // Cherokee folds to its capitals, which were encoded first.
+    match character as u32 {
+        0x13A0..=0x13F5 => Folding::Unchanged,
+        code @ 0x13F8..=0x13FD => {
+            Folding::Char(char::from_u32(code - 0x13F8 + 0x13F0).unwrap_or(character))
+        }
+        code @ 0xAB70..=0xABBF => {
+            Folding::Char(char::from_u32(code - 0xAB70 + 0x13A0).unwrap_or(character))
+        }
+        _ => {
+            let lowercase = character.to_lowercase();
+
+            if lowercase.len() == 1 && lowercase.clone().next() == Some(character) {
+                Folding::Unchanged
+            } else {
+                Folding::Lowercase(lowercase)
+            }
+        }
+    }
+}
+
+/// Characters whose case folding differs from `char::to_lowercase`, sorted by character: those
+/// that fold to several characters, and lowercase variants that fold to a common form, such as
+/// final sigma to sigma.
+const FOLDINGS: &[(char, &str)] = &[
+    ('\u{B5}', "\u{3BC}"),
+    ('\u{DF}', "ss"),
+    ('\u{149}', "\u{2BC}n"),
+    ('\u{17F}', "s"),
+    ('\u{1F0}', "j\u{30C}"),
+    ('\u{345}', "\u{3B9}"),
+    ('\u{390}', "\u{3B9}\u{308}\u{301}"),
+    ('\u{3B0}', "\u{3C5}\u{308}\u{301}"),
+    ('\u{3C2}', "\u{3C3}"),
+    ('\u{3D0}', "\u{3B2}"),
+    ('\u{3D1}', "\u{3B8}"),
+    ('\u{3D5}', "\u{3C6}"),
+    ('\u{3D6}', "\u{3C0}"),
+    ('\u{3F0}', "\u{3BA}"),
+    ('\u{3F1}', "\u{3C1}"),
+    ('\u{3F5}', "\u{3B5}"),
+    ('\u{587}', "\u{565}\u{582}"),
+    ('\u{1C80}', "\u{432}"),
+    ('\u{1C81}', "\u{434}"),
+    ('\u{1C82}', "\u{43E}"),
+    ('\u{1C83}', "\u{441}"),
+    ('\u{1C84}', "\u{442}"),
+    ('\u{1C85}', "\u{442}"),
+    ('\u{1C86}', "\u{44A}"),
+    ('\u{1C87}', "\u{463}"),
+    ('\u{1C88}', "\u{A64B}"),
+    ('\u{1E96}', "h\u{331}"),
+    ('\u{1E97}', "t\u{308}"),
+    ('\u{1E98}', "w\u{30A}"),
+    ('\u{1E99}', "y\u{30A}"),
+    ('\u{1E9A}', "a\u{2BE}"),
+    ('\u{1E9B}', "\u{1E61}"),
+    ('\u{1E9E}', "ss"),
+    ('\u{1F50}', "\u{3C5}\u{313}"),
+    ('\u{1F52}', "\u{3C5}\u{313}\u{300}"),
+    ('\u{1F54}', "\u{3C5}\u{313}\u{301}"),
+    ('\u{1F56}', "\u{3C5}\u{313}\u{342}"),
+    ('\u{1F80}', "\u{1F00}\u{3B9}"),
+    ('\u{1F81}', "\u{1F01}\u{3B9}"),
+    ('\u{1F82}', "\u{1F02}\u{3B9}"),
+    ('\u{1F83}', "\u{1F03}\u{3B9}"),
+    ('\u{1F84}', "\u{1F04}\u{3B9}"),
+    ('\u{1F85}', "\u{1F05}\u{3B9}"),
+    ('\u{1F86}', "\u{1F06}\u{3B9}"),
+    ('\u{1F87}', "\u{1F07}\u{3B9}"),
+    ('\u{1F88}', "\u{1F00}\u{3B9}"),
+    ('\u{1F89}', "\u{1F01}\u{3B9}"),
+    ('\u{1F8A}', "\u{1F02}\u{3B9}"),
+    ('\u{1F8B}', "\u{1F03}\u{3B9}"),
+    ('\u{1F8C}', "\u{1F04}\u{3B9}"),
+    ('\u{1F8D}', "\u{1F05}\u{3B9}"),
+    ('\u{1F8E}', "\u{1F06}\u{3B9}"),
+    ('\u{1F8F}', "\u{1F07}\u{3B9}"),
+    ('\u{1F90}', "\u{1F20}\u{3B9}"),
+    ('\u{1F91}', "\u{1F21}\u{3B9}"),
+    ('\u{1F92}', "\u{1F22}\u{3B9}"),
+    ('\u{1F93}', "\u{1F23}\u{3B9}"),
+    ('\u{1F94}', "\u{1F24}\u{3B9}"),
+    ('\u{1F95}', "\u{1F25}\u{3B9}"),
+    ('\u{1F96}', "\u{1F26}\u{3B9}"),
+    ('\u{1F97}', "\u{1F27}\u{3B9}"),
+    ('\u{1F98}', "\u{1F20}\u{3B9}"),
+    ('\u{1F99}', "\u{1F21}\u{3B9}"),
+    ('\u{1F9A}', "\u{1F22}\u{3B9}"),
+    ('\u{1F9B}', "\u{1F23}\u{3B9}"),
+    ('\u{1F9C}', "\u{1F24}\u{3B9}"),
+    ('\u{1F9D}', "\u{1F25}\u{3B9}"),
+    ('\u{1F9E}', "\u{1F26}\u{3B9}"),
+    ('\u{1F9F}', "\u{1F27}\u{3B9}"),
+    ('\u{1FA0}', "\u{1F60}\u{3B9}"),
+    ('\u{1FA1}', "\u{1F61}\u{3B9}"),
+    ('\u{1FA2}', "\u{1F62}\u{3B9}"),
+    ('\u{1FA3}', "\u{1F63}\u{3B9}"),
+    ('\u{1FA4}', "\u{1F64}\u{3B9}"),
+    ('\u{1FA5}', "\u{1F65}\u{3B9}"),
+    ('\u{1FA6}', "\u{1F66}\u{3B9}"),
+    ('\u{1FA7}', "\u{1F67}\u{3B9}"),
+    ('\u{1FA8}', "\u{1F60}\u{3B9}"),
+    ('\u{1FA9}', "\u{1F61}\u{3B9}"),
+    ('\u{1FAA}', "\u{1F62}\u{3B9}"),
+    ('\u{1FAB}', "\u{1F63}\u{3B9}"),
+    ('\u{1FAC}', "\u{1F64}\u{3B9}"),
+    ('\u{1FAD}', "\u{1F65}\u{3B9}"),
+    ('\u{1FAE}', "\u{1F66}\u{3B9}"),
+    ('\u{1FAF}', "\u{1F67}\u{3B9}"),
+    ('\u{1FB2}', "\u{1F70}\u{3B9}"),
+    ('\u{1FB3}', "\u{3B1}\u{3B9}"),
+    ('\u{1FB4}', "\u{3AC}\u{3B9}"),
+    ('\u{1FB6}', "\u{3B1}\u{342}"),
+    ('\u{1FB7}', "\u{3B1}\u{342}\u{3B9}"),
+    ('\u{1FBC}', "\u{3B1}\u{3B9}"),
+    ('\u{1FBE}', "\u{3B9}"),
+    ('\u{1FC2}', "\u{1F74}\u{3B9}"),
+    ('\u{1FC3}', "\u{3B7}\u{3B9}"),
+    ('\u{1FC4}', "\u{3AE}\u{3B9}"),
+    ('\u{1FC6}', "\u{3B7}\u{342}"),
+    ('\u{1FC7}', "\u{3B7}\u{342}\u{3B9}"),
+    ('\u{1FCC}', "\u{3B7}\u{3B9}"),
+    ('\u{1FD2}', "\u{3B9}\u{308}\u{300}"),
+    ('\u{1FD3}', "\u{3B9}\u{308}\u{301}"),
+    ('\u{1FD6}', "\u{3B9}\u{342}"),
+    ('\u{1FD7}', "\u{3B9}\u{308}\u{342}"),
+    ('\u{1FE2}', "\u{3C5}\u{308}\u{300}"),
+    ('\u{1FE3}', "\u{3C5}\u{308}\u{301}"),
+    ('\u{1FE4}', "\u{3C1}\u{313}"),
+    ('\u{1FE6}', "\u{3C5}\u{342}"),
+    ('\u{1FE7}', "\u{3C5}\u{308}\u{342}"),
+    ('\u{1FF2}', "\u{1F7C}\u{3B9}"),
+    ('\u{1FF3}', "\u{3C9}\u{3B9}"),
+    ('\u{1FF4}', "\u{3CE}\u{3B9}"),
+    ('\u{1FF6}', "\u{3C9}\u{342}"),
+    ('\u{1FF7}', "\u{3C9}\u{342}\u{3B9}"),
+    ('\u{1FFC}', "\u{3C9}\u{3B9}"),
+    ('\u{FB00}', "ff"),
+    ('\u{FB01}', "fi"),
+    ('\u{FB02}', "fl"),
+    ('\u{FB03}', "ffi"),
+    ('\u{FB04}', "ffl"),
+    ('\u{FB05}', "st"),
+    ('\u{FB06}', "st"),
+    ('\u{FB13}', "\u{574}\u{576}"),
+    ('\u{FB14}', "\u{574}\u{565}"),
+    ('\u{FB15}', "\u{574}\u{56B}"),
+    ('\u{FB16}', "\u{57E}\u{576}"),
+    ('\u{FB17}', "\u{574}\u{56D}"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_borrow_tokens_that_need_no_folding() {
+        let words = Words::new("plain Words");
+        let tokens: Vec<Cow<str>> = words.tokenize_borrowed().collect();
+
+        assert!(matches!(tokens[0], Cow::Borrowed("plain")));
+        assert!(matches!(&tokens[1], Cow::Owned(word) if word == "words"));
+    }
+
+    fn tokens(tokenizer: WordTokenizer, text: &str) -> Vec<String> {
+        tokenizer.tokens(text).map(Cow::into_owned).collect()
+    }
+
+    #[test]
+    fn foldings_are_sorted_for_binary_search() {
+        assert!(FOLDINGS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn case_folding_expands_and_merges_variants() {
+        assert_eq!(fold_case("Straße"), "strasse");
+        assert_eq!(fold_case("STRASSE"), "strasse");
+        assert_eq!(fold_case("λόγος"), "λόγοσ");
+        assert_eq!(fold_case("ΛΌΓΟΣ"), "λόγοσ");
+        assert_eq!(fold_case("ſ"), "s");
+    }
+
+    #[test]
+    fn case_folding_maps_cherokee_to_its_capitals() {
+        assert_eq!(fold_case("\u{13A0}"), "\u{13A0}");
+        assert_eq!(fold_case("\u{13F8}"), "\u{13F0}");
+        assert_eq!(fold_case("\u{AB70}\u{ABBF}"), "\u{13A0}\u{13EF}");
+    }
+
+    #[test]
+    fn case_folding_borrows_text_that_is_already_folded() {
+        assert!(matches!(fold_case("prórroga"), Cow::Borrowed("prórroga")));
+        assert!(matches!(fold_case("Prórroga"), Cow::Owned(folded) if folded == "prórroga"));
+    }
+
+    #[test]
+    fn accented_words_stay_whole() {
+        let tokenizer = WordTokenizer::new();
+
+        assert_eq!(
+            tokens(tokenizer, "La prórroga, Démocratie!"),
+            ["la", "prórroga", "démocratie"]
+        );
+    }
+
+    #[test]
+    fn apostrophes_are_kept_split_or_removed() {
+        let tokenizer = WordTokenizer::new();
+        let text = "d’euros don't";
+
+        assert_eq!(tokens(tokenizer, text), ["d'euros", "don't"]);
+        assert_eq!(
+            tokens(tokenizer.with_apostrophes(Apostrophes::Split), text),
+            ["d", "euros", "don", "t"]
+        );
+        assert_eq!(
+            tokens(tokenizer.with_apostrophes(Apostrophes::Remove), text),
+            ["deuros", "dont"]
+        );
+    }
+
+    #[test]
+    fn hyphenated_compounds_are_split_kept_or_joined() {
+        let tokenizer = WordTokenizer::new();
+        let text = "critical-care beds, long\u{2010}term - care";
+
+        assert_eq!(
+            tokens(tokenizer, text),
+            ["critical", "care", "beds", "long", "term", "care"]
+        );
+        assert_eq!(
+            tokens(tokenizer.with_hyphens(Hyphens::Keep), text),
+            ["critical-care", "beds", "long-term", "care"]
+        );
+        assert_eq!(
+            tokens(tokenizer.with_hyphens(Hyphens::Remove), text),
+            ["criticalcare", "beds", "longterm", "care"]
+        );
+    }
+
+    #[test]
+    fn apostrophes_and_hyphens_combine() {
+        let tokenizer = WordTokenizer::new()
+            .with_apostrophes(Apostrophes::Split)
+            .with_hyphens(Hyphens::Keep);
+
+        assert_eq!(tokens(tokenizer, "l’arc-en-ciel"), ["l", "arc-en-ciel"]);
+    }
+}
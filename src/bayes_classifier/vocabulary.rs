@@ -0,0 +1,338 @@
+use super::{DataType, Number, Token};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Interned tokens with their per-class counts stored as dense rows, one column per class.
+/// Alongside the occurrence counts, `documents` holds how many training documents of each class
+/// contained the token.
+///
+/// Rows of removed tokens are zeroed and reused by the next token interned.
+#[derive(Clone)]
+pub(crate) struct Vocabulary<K: Token = DataType> {
+    ids: HashMap<K, usize>,
+    free_rows: Vec<usize>,
+    totals: Vec<Number>,
+    counts: Vec<Number>,
+    documents: Vec<Number>,
+    columns: usize,
+}
+
+impl<K: Token> Default for Vocabulary<K> {
+    fn default() -> Vocabulary<K> {
+        Vocabulary::with_columns(0)
+    }
+}
+
+impl<K: Token> Vocabulary<K> {
+    pub fn with_columns(columns: usize) -> Vocabulary<K> {
+        Vocabulary {
+            ids: HashMap::new(),
+            free_rows: Vec::new(),
+            totals: Vec::new(),
+            counts: Vec::new(),
+            documents: Vec::new(),
+            columns,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn id<Q: Hash + Eq + ?Sized>(&self, datum: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+    {
+        self.ids.get(datum).cloned()
+    }
+
+    pub fn contains<Q: Hash + Eq + ?Sized>(&self, datum: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.ids.contains_key(datum)
+    }
+
+    pub fn intern(&mut self, datum: K) -> usize {
+        if let Some(&id) = self.ids.get(&datum) {
+            return id;
+        }
+
+        let id = match self.free_rows.pop() {
+            Some(id) => id,
+            None => {
+                self.totals.push(0.0);
+                self.counts.resize(self.counts.len() + self.columns, 0.0);
+                self.documents
+                    .resize(self.documents.len() + self.columns, 0.0);
+                self.totals.len() - 1
+            }
+        };
+
+        self.ids.insert(datum, id);
+        id
+    }
+
+    pub fn row(&self, id: usize) -> &[Number] {
+        &self.counts[id * self.columns..(id + 1) * self.columns]
+    }
+
+    pub fn document_row(&self, id: usize) -> &[Number] {
+        &self.documents[id * self.columns..(id + 1) * self.columns]
+    }
+
+    pub fn total(&self, id: usize) -> Number {
+        self.totals[id]
+    }
+
+    pub fn add(&mut self, id: usize, column: usize, amount: Number) {
+        self.totals[id] += amount;
+        self.counts[id * self.columns + column] += amount;
+    }
+
+    pub fn add_documents(&mut self, id: usize, column: usize, amount: Number) {
+        self.documents[id * self.columns + column] += amount;
+    }
+
+    pub fn set_documents(&mut self, id: usize, column: usize, count: Number) {
+        self.documents[id * self.columns + column] = count;
+    }
+
+    pub fn estimate_documents(&mut self) {
+        self.documents = self.counts.clone();
+    }
+
+    pub fn set(&mut self, id: usize, column: usize, count: Number) {
+        let cell = &mut self.counts[id * self.columns + column];
+
+        self.totals[id] += count - *cell;
+        *cell = count;
+    }
+
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, datum: &Q)
+    where
+        K: Borrow<Q>,
+    {
+        if let Some(id) = self.ids.remove(datum) {
+            self.totals[id] = 0.0;
+            for count in self.counts[id * self.columns..(id + 1) * self.columns].iter_mut() {
+                *count = 0.0;
+            }
+            for count in self.documents[id * self.columns..(id + 1) * self.columns].iter_mut() {
+                *count = 0.0;
+            }
+            self.free_rows.push(id);
+        }
+    }
+
+    pub fn push_column(&mut self) {
+        let rows = self.totals.len();
+
+        self.counts = with_column_inserted(&self.counts, rows, self.columns);
+        self.documents = with_column_inserted(&self.documents, rows, self.columns);
+        self.columns += 1;
+    }
+
+    pub fn remove_column(&mut self, column: usize) {
+        for (id, total) in self.totals.iter_mut().enumerate() {
+            *total -= self.counts[id * self.columns + column];
+        }
+
+        self.counts = with_column_removed(&self.counts, self.columns, column);
+        self.documents = with_column_removed(&self.documents, self.columns, column);
+        self.columns -= 1;
+    }
+
+    /// Checks that the layout read from a saved model is one this type could have built, with
+    /// one column per class.
+    #[cfg(feature = "serde")]
+    pub fn validate(&self, columns: usize) -> Result<(), String> {
+        let rows = self.totals.len();
+
+        if self.columns != columns {
+            return Err(format!(
+                "{} count columns for {} classes",
+                self.columns, columns
+            ));
+        }
+        if self.counts.len() != rows * columns || self.documents.len() != rows * columns {
+            return Err(format!(
+                "{} counts and {} document counts for {} rows of {} columns",
+                self.counts.len(),
+                self.documents.len(),
+                rows,
+                columns
+            ));
+        }
+
+        let mut used = vec![false; rows];
+        for &id in self.ids.values().chain(self.free_rows.iter()) {
+            match used.get_mut(id) {
+                Some(used) if !*used => *used = true,
+                Some(_) => return Err(format!("row {} is used twice", id)),
+                None => return Err(format!("row {} is out of range", id)),
+            }
+        }
+        if used.contains(&false) {
+            return Err("some rows are neither used nor free".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Row ids of every interned token, in no particular order.
+    pub fn ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.ids.values().cloned()
+    }
+
+    /// Interned tokens and their row ids, in row order.
+    pub fn entries(&self) -> Vec<(&K, usize)> {
+        let mut entries: Vec<(&K, usize)> =
+            self.ids.iter().map(|(datum, &id)| (datum, id)).collect();
+        entries.sort_by_key(|&(_, id)| id);
+        entries
+    }
+}
+
+/// Saved as the tokens in row order, so that tokens need not be usable as JSON keys, with the
+/// rows of removed tokens left out.
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct SavedVocabulary<'a, K> {
+    tokens: Vec<&'a K>,
+    columns: usize,
+    counts: Vec<Number>,
+    documents: Vec<Number>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoadedVocabulary<K: Token> {
+    Rows {
+        tokens: Vec<K>,
+        columns: usize,
+        counts: Vec<Number>,
+        documents: Vec<Number>,
+    },
+    /// Layout written by format versions 2 to 4, which mapped each token to its row.
+    Interned {
+        ids: HashMap<K, usize>,
+        free_rows: Vec<usize>,
+        totals: Vec<Number>,
+        counts: Vec<Number>,
+        #[serde(default)]
+        documents: Vec<Number>,
+        columns: usize,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl<K: Token + Serialize> Serialize for Vocabulary<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries = self.entries();
+        let rows = |matrix: &[Number]| -> Vec<Number> {
+            entries
+                .iter()
+                .flat_map(|&(_, id)| matrix[id * self.columns..(id + 1) * self.columns].iter())
+                .cloned()
+                .collect()
+        };
+
+        SavedVocabulary {
+            tokens: entries.iter().map(|&(datum, _)| datum).collect(),
+            columns: self.columns,
+            counts: rows(&self.counts),
+            documents: rows(&self.documents),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K: Token + Deserialize<'de>> Deserialize<'de> for Vocabulary<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Vocabulary<K>, D::Error> {
+        Ok(match LoadedVocabulary::deserialize(deserializer)? {
+            LoadedVocabulary::Rows {
+                tokens,
+                columns,
+                counts,
+                documents,
+            } => {
+                let totals = (0..tokens.len())
+                    .map(|id| {
+                        counts
+                            .get(id * columns..(id + 1) * columns)
+                            .map_or(0.0, |row| row.iter().sum())
+                    })
+                    .collect();
+
+                Vocabulary {
+                    ids: tokens
+                        .into_iter()
+                        .enumerate()
+                        .map(|(id, datum)| (datum, id))
+                        .collect(),
+                    free_rows: Vec::new(),
+                    totals,
+                    counts,
+                    documents,
+                    columns,
+                }
+            }
+            LoadedVocabulary::Interned {
+                ids,
+                free_rows,
+                totals,
+                counts,
+                documents,
+                columns,
+            } => Vocabulary {
+                ids,
+                free_rows,
+                totals,
+                counts,
+                documents,
+                columns,
+            },
+        })
+    }
+}
+
+fn with_column_inserted(matrix: &[Number], rows: usize, columns: usize) -> Vec<Number> {
+    let mut inserted = Vec::with_capacity(rows * (columns + 1));
+
+    for id in 0..rows {
+        inserted.extend_from_slice(&matrix[id * columns..(id + 1) * columns]);
+        inserted.push(0.0);
+    }
+
+    inserted
+}
+
+fn with_column_removed(matrix: &[Number], columns: usize, column: usize) -> Vec<Number> {
+    let mut removed = Vec::with_capacity(matrix.len() / columns * (columns - 1));
+
+    for row in matrix.chunks_exact(columns) {
+        removed.extend_from_slice(&row[..column]);
+        removed.extend_from_slice(&row[column + 1..]);
+    }
+
+    removed
+}
+
+/// Looks up a borrowed token, such as a `str` in a vocabulary of `String`s.
+///
+/// Written against `B::Owned` rather than the vocabulary's token type so that the compiler can
+/// see `B::Owned: Borrow<B>`, which it loses once `B::Owned` is known to be a concrete type.
+pub(crate) fn borrowed_id<B>(vocabulary: &Vocabulary<B::Owned>, datum: &B) -> Option<usize>
+where
+    B: ?Sized + Hash + Eq + ToOwned,
+    B::Owned: Token,
+{
+    vocabulary.id(datum)
+}